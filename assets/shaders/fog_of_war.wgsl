@@ -6,9 +6,13 @@ var<uniform> color: vec4<f32>;
 var fog_texture: texture_2d<f32>;
 @group(2) @binding(2)
 var fog_sampler: sampler;
+@group(2) @binding(3)
+var<uniform> explored_color: vec4<f32>;
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    let fog_value = textureSample(fog_texture, fog_sampler, in.uv).r;
-    return vec4<f32>(color.rgb, color.a * fog_value);
-}
\ No newline at end of file
+    let fog = textureSample(fog_texture, fog_sampler, in.uv);
+    // red is how thick the fog is right now, green whether the square has been explored before
+    let fog_color = mix(color, explored_color, fog.g);
+    return vec4<f32>(fog_color.rgb, fog_color.a * fog.r);
+}
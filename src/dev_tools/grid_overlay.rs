@@ -5,14 +5,21 @@ use bevy::prelude::*;
 
 use crate::dev_tools::DebugOverlaysState;
 use crate::game::grid::grid_layout::GridLayout;
+use crate::game::grid::ray_cast::RayCast;
 use crate::game::grid::{GridPosition, GridSprite};
+use crate::game::line_of_sight::vision::Facing;
+use crate::game::spawn::level::LevelVisionBlockers;
 use crate::game::spawn::player::Player;
 use crate::screen::Screen;
 
 pub fn plugin(app: &mut App) {
     app.add_systems(
         Update,
-        (update_grid_debug_overlay, update_player_grid_debug_overlay)
+        (
+            update_grid_debug_overlay,
+            update_player_grid_debug_overlay,
+            draw_player_facing_ray,
+        )
             .run_if(in_state(DebugOverlaysState::Enabled)),
     );
 }
@@ -39,6 +46,40 @@ fn update_player_grid_debug_overlay(
     }
 }
 
+/// How far ahead of the player to look for a wall
+const FACING_RAY_LENGTH: f32 = 20.;
+
+/// A line from the player to the first wall they're facing, with a tick showing which way that wall faces
+fn draw_player_facing_ray(
+    mut gizmos: Gizmos,
+    grid: Res<GridLayout>,
+    blockers: Res<LevelVisionBlockers>,
+    query: Query<(&GridPosition, &Facing), With<Player>>,
+) {
+    let to_world = |point: Vec2| grid.grid_to_world(&GridPosition::new(point.x, point.y));
+    for (position, facing) in query.iter() {
+        let origin = position.coordinates + position.offset;
+        let Some(hit) = blockers.ray_cast(origin, facing.0, FACING_RAY_LENGTH) else {
+            gizmos.line_2d(
+                to_world(origin),
+                to_world(origin + facing.0.normalize_or_zero() * FACING_RAY_LENGTH),
+                Color::srgba(0.0, 1.0, 0.0, 1.0),
+            );
+            continue;
+        };
+        gizmos.line_2d(
+            to_world(origin),
+            to_world(hit.point),
+            Color::srgba(1.0, 0.5, 0.0, 1.0),
+        );
+        gizmos.line_2d(
+            to_world(hit.point),
+            to_world(hit.point + hit.normal * 0.5),
+            Color::srgba(0.0, 0.5, 1.0, 1.0),
+        );
+    }
+}
+
 fn update_grid_debug_overlay(
     mut commands: Commands,
     grid: Res<GridLayout>,
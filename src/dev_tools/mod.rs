@@ -1,5 +1,6 @@
 //! Development tools for the game. This plugin is only enabled in dev builds.
 
+use crate::game::ghost::{ExportGhost, ImportGhost};
 use crate::game::spawn::health::ReceiveDamage;
 use crate::game::spawn::player::Player;
 use crate::input::DevActionToggles;
@@ -20,7 +21,10 @@ pub(super) fn plugin(app: &mut App) {
     app.add_systems(Update, log_transitions::<DebugOverlaysState>);
     app.add_systems(Startup, spawn_dev_input_manager);
 
-    app.add_systems(Update, (toggle_debug_overlays, kill_player));
+    app.add_systems(
+        Update,
+        (toggle_debug_overlays, kill_player, export_import_ghost),
+    );
 
     // press F1 in dev builds to open an entity inspector
     app.init_state::<WorldInspectorState>()
@@ -94,6 +98,20 @@ fn kill_player(
     }
 }
 
+/// where F5 saves the current attempt, and F6 loads it back as a ghost
+const DEV_GHOST_PATH: &str = "ghost.ghost";
+
+fn export_import_ghost(mut commands: Commands, query: Query<&ActionState<DevActionToggles>>) {
+    for act in query.iter() {
+        if act.just_pressed(&DevActionToggles::ExportGhost) {
+            commands.trigger(ExportGhost(DEV_GHOST_PATH.into()));
+        }
+        if act.just_pressed(&DevActionToggles::ImportGhost) {
+            commands.trigger(ImportGhost(DEV_GHOST_PATH.into()));
+        }
+    }
+}
+
 mod enemy_vision {
     use bevy::app::App;
     use bevy::prelude::*;
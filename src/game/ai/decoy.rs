@@ -0,0 +1,218 @@
+//! Hunters can mistake the ghosts of the player's past lives for the real thing.
+//! How they react is set per level in LDtk with the `GhostDecoys` level field, so players can deliberately die
+//! somewhere to plant a decoy for their next loop.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_ecs_ldtk::ldtk::ldtk_fields::{LdtkFields, LdtkFieldsError};
+use bevy_ecs_ldtk::prelude::LevelMetadataAccessor;
+use bevy_ecs_ldtk::LevelEvent;
+
+use crate::game::ai::search::{SearchSettings, SearchState};
+use crate::game::ai::suspicion::Suspicion;
+use crate::game::ai::{main_ai_behavior_system, AiState, HasAiState};
+use crate::game::ghost::personal_best::PersonalBestGhost;
+use crate::game::ghost::Ghost;
+use crate::game::grid::GridPosition;
+use crate::game::line_of_sight::vision::VisibleSquares;
+use crate::game::line_of_sight::CanRevealFog;
+use crate::game::spawn::enemy::{CanSeePlayer, Enemy};
+use crate::game::spawn::player::Player;
+use crate::screen::Screen;
+use crate::AppSet;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<GhostDecoys>();
+
+    // systems
+    app.add_systems(Update, read_level_decoy_rule);
+    app.add_systems(
+        FixedUpdate,
+        react_to_decoys
+            .before(main_ai_behavior_system)
+            .run_if(in_state(Screen::Playing))
+            .in_set(AppSet::UpdateAi),
+    );
+
+    // reflection
+    app.register_type::<GhostDecoys>();
+    app.register_type::<DecoyChase>();
+}
+
+/// How long a hunter follows a ghost before it loses interest and searches where it last saw it
+const DECOY_CHASE_DURATION: Duration = Duration::from_secs(4);
+
+/// How hunters in the current level react to seeing a live ghost
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum GhostDecoys {
+    /// Ghosts are just for the player's benefit
+    #[default]
+    Ignored,
+    /// Hunters stop and stare at ghosts, without ever raising the alarm
+    Suspicious,
+    /// Hunters chase ghosts for a while, then search around where they last saw them
+    Chased,
+}
+
+impl GhostDecoys {
+    fn from_ldtk(value: &str) -> Option<Self> {
+        match value {
+            "Ignored" => Some(Self::Ignored),
+            "Suspicious" => Some(Self::Suspicious),
+            "Chased" => Some(Self::Chased),
+            _ => None,
+        }
+    }
+}
+
+/// A hunter that has been lured away by a ghost. It won't fall for another one until it's back at its post.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct DecoyChase {
+    /// Keeps following the ghost until this finishes
+    timer: Timer,
+}
+
+/// Picks up the decoy rule for each level as it spawns. Levels without one ignore ghosts.
+fn read_level_decoy_rule(
+    mut level_events: EventReader<LevelEvent>,
+    ldtk_project_entities: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut decoys: ResMut<GhostDecoys>,
+) {
+    for level_event in level_events.read() {
+        let LevelEvent::Spawned(level_iid) = level_event else {
+            continue;
+        };
+        let Some(level) = ldtk_project_entities
+            .get_single()
+            .ok()
+            .and_then(|handle| ldtk_project_assets.get(handle))
+            .and_then(|project| project.get_raw_level_by_iid(level_iid.get()))
+        else {
+            continue;
+        };
+        let rule = match level.get_maybe_enum_field("GhostDecoys") {
+            Ok(Some(value)) => GhostDecoys::from_ldtk(value).unwrap_or_else(|| {
+                warn!(
+                    "Level {} has unknown GhostDecoys value {:?}, ignoring ghosts",
+                    level.identifier, value
+                );
+                GhostDecoys::default()
+            }),
+            Ok(None) | Err(LdtkFieldsError::FieldNotFound { .. }) => GhostDecoys::default(),
+            Err(error) => {
+                warn!(
+                    "Level {} has a broken field: {}, ignoring ghosts",
+                    level.identifier, error
+                );
+                GhostDecoys::default()
+            }
+        };
+        decoys.set_if_neq(rule);
+    }
+}
+
+fn react_to_decoys(
+    decoys: Res<GhostDecoys>,
+    // only living ghosts of past lives look like the player, never the personal best replaying an older run
+    ghosts: Query<&GridPosition, (With<Ghost>, With<CanRevealFog>, Without<PersonalBestGhost>)>,
+    player: Query<&GridPosition, With<Player>>,
+    mut hunters: Query<
+        (
+            Entity,
+            &GridPosition,
+            &VisibleSquares,
+            &mut HasAiState,
+            &mut Suspicion,
+            &mut SearchState,
+            Option<&mut DecoyChase>,
+            Has<CanSeePlayer>,
+        ),
+        With<Enemy>,
+    >,
+    search_settings: Res<SearchSettings>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    if *decoys == GhostDecoys::Ignored {
+        return;
+    }
+    let player_position = player.get_single().ok();
+
+    for (hunter, position, vision, mut ai, mut suspicion, mut search, chase, can_see_player) in
+        &mut hunters
+    {
+        // the real player always takes priority
+        if can_see_player || player_position.is_some_and(|player| vision.contains(player)) {
+            continue;
+        }
+        let closest_ghost = ghosts
+            .iter()
+            .filter(|ghost| vision.contains(ghost))
+            .min_by(|a, b| {
+                let distance = |ghost: &&GridPosition| position.direction_to(ghost).length();
+                distance(a).total_cmp(&distance(b))
+            });
+
+        match *decoys {
+            GhostDecoys::Ignored => {}
+            GhostDecoys::Suspicious => {
+                if let Some(ghost) = closest_ghost {
+                    suspicion.notice_decoy(*ghost, time.delta_seconds());
+                }
+            }
+            GhostDecoys::Chased => match chase {
+                Some(mut chase) => {
+                    if ai.current_state != AiState::Searching
+                        && ai.current_state != AiState::ReturningToPost
+                    {
+                        commands.entity(hunter).remove::<DecoyChase>();
+                        continue;
+                    }
+                    chase.timer.tick(time.delta());
+                    if !chase.timer.finished() {
+                        if let Some(ghost) = closest_ghost {
+                            search.begin(*ghost, &search_settings);
+                        }
+                    }
+                }
+                None => {
+                    let Some(ghost) = closest_ghost else {
+                        continue;
+                    };
+                    if ai.current_state == AiState::Searching {
+                        // already looking for the real player
+                        continue;
+                    }
+                    search.begin(*ghost, &search_settings);
+                    ai.transition_to(AiState::Searching);
+                    ai.is_away_from_post = true;
+                    commands.entity(hunter).insert(DecoyChase {
+                        timer: Timer::new(DECOY_CHASE_DURATION, TimerMode::Once),
+                    });
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_ldtk_value() {
+        for (value, rule) in [
+            ("Ignored", GhostDecoys::Ignored),
+            ("Suspicious", GhostDecoys::Suspicious),
+            ("Chased", GhostDecoys::Chased),
+        ] {
+            assert_eq!(GhostDecoys::from_ldtk(value), Some(rule));
+        }
+        assert_eq!(GhostDecoys::from_ldtk("Friendly"), None);
+    }
+}
@@ -1,18 +1,32 @@
 use bevy::app::App;
 use bevy::prelude::*;
 
-use crate::game::ai::AiState::{Idle, Patrolling, ReturnedToPost, ReturningToPost};
+use crate::game::ai::suspicion::Suspicion;
+use crate::game::ai::AiState::{
+    Chasing, Idle, Patrolling, ReturnedToPost, ReturningToPost, Searching, Suspicious,
+};
 use crate::game::spawn::enemy::CanSeePlayer;
 use crate::screen::Screen;
 use crate::AppSet;
 
+pub mod decoy;
+pub mod pathfinding;
+pub mod search;
+pub mod suspicion;
+
 pub fn plugin(app: &mut App) {
     // plugins
-    app.add_plugins(patrol::plugin);
+    app.add_plugins((
+        patrol::plugin,
+        decoy::plugin,
+        pathfinding::plugin,
+        search::plugin,
+        suspicion::plugin,
+    ));
 
     // systems
     app.add_systems(
-        Update,
+        FixedUpdate,
         (main_ai_behavior_system)
             .chain()
             .run_if(in_state(Screen::Playing))
@@ -33,6 +47,7 @@ pub struct _Prey;
 
 // It's wrapping an enum to ensure we only have one of these at a time
 #[derive(Component, Default, Copy, Clone, Eq, PartialEq, Reflect)]
+#[reflect(Component)]
 pub struct HasAiState {
     pub current_state: AiState,
     pub previous_state: AiState,
@@ -40,11 +55,20 @@ pub struct HasAiState {
     pub is_away_from_post: bool,
 }
 
+impl HasAiState {
+    /// Moves to a new state, remembering the one we came from
+    pub fn transition_to(&mut self, state: AiState) {
+        self.previous_state = self.current_state;
+        self.current_state = state;
+    }
+}
+
 #[derive(Default, Copy, Clone, Eq, PartialEq, Reflect)]
 pub enum AiState {
     #[default]
     Idle,
     Patrolling,
+    Suspicious,
     Chasing,
     Searching,
     ReturningToPost,
@@ -53,7 +77,7 @@ pub enum AiState {
 
 pub fn main_ai_behavior_system(
     mut aware_ais: Query<&mut HasAiState, Added<CanSeePlayer>>,
-    mut unaware_ais: Query<&mut HasAiState, Without<CanSeePlayer>>,
+    mut unaware_ais: Query<(&mut HasAiState, Option<&Suspicion>), Without<CanSeePlayer>>,
 ) {
     for mut ai in aware_ais.iter_mut() {
         if ai.current_state != AiState::Chasing {
@@ -62,8 +86,27 @@ pub fn main_ai_behavior_system(
             ai.is_away_from_post = true;
         }
     }
-    for mut ai in unaware_ais.iter_mut() {
-        if ai.is_away_from_post
+    for (mut ai, suspicion) in unaware_ais.iter_mut() {
+        let is_suspicious = suspicion.is_some_and(Suspicion::is_suspicious);
+        if ai.current_state == Chasing {
+            // we just lost sight of the player, go look for them
+            ai.transition_to(Searching);
+        } else if ai.current_state == Searching {
+            // the search system decides when we give up
+            continue;
+        } else if ai.current_state == Suspicious {
+            // we've calmed down, go back to what we were doing
+            if !is_suspicious {
+                if ai.is_away_from_post {
+                    ai.transition_to(ReturningToPost);
+                } else {
+                    let previous_state = ai.previous_state;
+                    ai.transition_to(previous_state);
+                }
+            }
+        } else if is_suspicious {
+            ai.transition_to(Suspicious);
+        } else if ai.is_away_from_post
             && ai.current_state != ReturningToPost
             && ai.current_state != ReturnedToPost
         {
@@ -81,7 +124,6 @@ pub fn main_ai_behavior_system(
             }
         }
     }
-    //TODO search for player
 }
 
 pub mod patrol {
@@ -94,7 +136,7 @@ pub mod patrol {
     use crate::game::grid::GridPosition;
     use crate::game::line_of_sight::vision::Facing;
     use crate::game::movement::GridMovement;
-    use crate::game::spawn::enemy::{Enemy, ENEMY_PATROL_SPEED};
+    use crate::game::spawn::enemy::{Enemy, EnemySpeed};
     use crate::game::threat::{ThreatTimer, ThreatTimerSettings};
     use crate::screen::Screen;
     use crate::AppSet::UpdateAi;
@@ -102,7 +144,7 @@ pub mod patrol {
     pub fn plugin(app: &mut App) {
         // systems
         app.add_systems(
-            Update,
+            FixedUpdate,
             follow_patrol_route
                 .run_if(in_state(Screen::Playing))
                 .in_set(UpdateAi),
@@ -123,6 +165,7 @@ pub mod patrol {
                 &mut Facing,
                 &mut GridMovement,
                 &HasAiState,
+                &EnemySpeed,
             ),
             With<Enemy>,
         >,
@@ -130,7 +173,9 @@ pub mod patrol {
         threat_timer: ResMut<ThreatTimer>,
         time: Res<Time>,
     ) {
-        for (mut state, route, entity_position, mut facing, mut movement, ai) in query.iter_mut() {
+        for (mut state, route, entity_position, mut facing, mut movement, ai, speed) in
+            query.iter_mut()
+        {
             if ai.current_state != AiState::Patrolling || route.waypoints.is_empty() {
                 continue;
             }
@@ -143,13 +188,13 @@ pub mod patrol {
                 movement.acceleration_player_force = Vec2::ZERO;
                 // we've waited here long enough, advance the waypoint
                 if state.wait_timer.finished() {
-                    state.wait_timer.reset();
-                    state.next_waypoint(route);
+                    let next = state.next_waypoint(route);
+                    state.wait_timer = Timer::new(route.waypoints[next].wait_time, TimerMode::Once);
                 }
             }
             // we're not at our target yet, so move towards it
             else {
-                let accel: f32 = ENEMY_PATROL_SPEED
+                let accel: f32 = speed.patrol
                     * (1.0 + threat_timer.current_level as f32 / threat_settings.levels as f32);
                 movement.acceleration_player_force = direction_to_waypoint.normalize() * accel;
             }
@@ -173,6 +218,11 @@ pub mod patrol {
 
     impl PatrolState {
         pub fn next_waypoint(&mut self, patrol_route: &PatrolRoute) -> usize {
+            // a single point is somewhere to stand, not a route, whatever the mode
+            if patrol_route.waypoints.len() < 2 {
+                self.current_waypoint = 0;
+                return 0;
+            }
             let current = self.current_waypoint;
             let direction = self.direction;
             let next = match patrol_route.mode {
@@ -218,4 +268,45 @@ pub mod patrol {
         pub state: PatrolState,
         pub route: PatrolRoute,
     }
+
+    #[cfg(test)]
+    mod tests {
+        use test_case::test_case;
+
+        use super::*;
+
+        fn route(points: usize, mode: PatrolMode) -> PatrolRoute {
+            PatrolRoute {
+                waypoints: (0..points)
+                    .map(|i| PatrolWaypoint {
+                        position: GridPosition::new(i as f32, 0.),
+                        facing: Facing::default(),
+                        wait_time: Duration::ZERO,
+                    })
+                    .collect(),
+                mode,
+            }
+        }
+
+        fn visit(points: usize, mode: PatrolMode, steps: usize) -> Vec<usize> {
+            let route = route(points, mode);
+            let mut state = PatrolState {
+                direction: 1,
+                ..default()
+            };
+            (0..steps).map(|_| state.next_waypoint(&route)).collect()
+        }
+
+        #[test_case(PatrolMode::Cycle, vec![1, 2, 0, 1, 2] ; "cycle")]
+        #[test_case(PatrolMode::PingPong, vec![1, 2, 1, 0, 1] ; "ping pong")]
+        fn follows_the_route(mode: PatrolMode, expected: Vec<usize>) {
+            assert_eq!(visit(3, mode, 5), expected);
+        }
+
+        #[test_case(PatrolMode::Cycle ; "cycle")]
+        #[test_case(PatrolMode::PingPong ; "ping pong")]
+        fn stays_put_on_a_one_point_route(mode: PatrolMode) {
+            assert_eq!(visit(1, mode, 3), vec![0, 0, 0]);
+        }
+    }
 }
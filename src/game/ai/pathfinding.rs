@@ -0,0 +1,319 @@
+//! A* pathfinding over the level grid, so hunters walk around walls instead of into them.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use bevy::prelude::*;
+
+use crate::game::grid::GridPosition;
+use crate::game::spawn::level::LevelWalls;
+use crate::AppSet;
+
+pub fn plugin(app: &mut App) {
+    // systems
+    app.add_systems(
+        FixedUpdate,
+        invalidate_paths_when_walls_change.in_set(AppSet::UpdateVirtualGrid),
+    );
+
+    // reflection
+    app.register_type::<NavigationPath>();
+}
+
+/// Cost of moving to an orthogonal neighbor. Diagonals cost roughly sqrt(2) times as much.
+const ORTHOGONAL_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// How close (in grid units) we have to get to a waypoint before steering towards the next one
+const WAYPOINT_REACHED_DISTANCE: f32 = 0.25;
+
+/// If we end up further than this from the next waypoint (e.g. we got pushed), the path is replanned
+const OFF_PATH_DISTANCE: f32 = 1.5;
+
+/// A cached path through the level to a goal square.
+/// The path is only replanned when the goal square changes, the walls change, or we wander off of it.
+#[derive(Component, Reflect, Debug, Clone, Default)]
+#[reflect(Component)]
+pub struct NavigationPath {
+    goal: Option<IVec2>,
+    waypoints: Vec<IVec2>,
+    current_waypoint: usize,
+}
+
+impl NavigationPath {
+    /// Forget the current path, so it gets replanned the next time we steer
+    pub fn invalidate(&mut self) {
+        self.goal = None;
+        self.waypoints.clear();
+        self.current_waypoint = 0;
+    }
+
+    /// Returns the direction to move in to get from `from` to `to`, following walkable squares.
+    /// Falls back to a straight line if there's no path at all, so we at least try to get closer.
+    pub fn steer(&mut self, walls: &LevelWalls, from: &GridPosition, to: &GridPosition) -> Vec2 {
+        let start = grid_square(from);
+        let goal = grid_square(to);
+
+        // already in the goal square, so just home in on the exact position
+        if start == goal {
+            return from.direction_to(to);
+        }
+
+        if self.goal != Some(goal) || self.is_off_path(from) {
+            self.replan(walls, start, goal);
+        }
+
+        while let Some(&waypoint) = self.waypoints.get(self.current_waypoint) {
+            let direction = from.direction_to(&GridPosition::from_ivec(&waypoint));
+            if direction.length() > WAYPOINT_REACHED_DISTANCE {
+                return direction;
+            }
+            self.current_waypoint += 1;
+        }
+
+        // ran out of waypoints (or never had any), head straight for the target
+        from.direction_to(to)
+    }
+
+    fn replan(&mut self, walls: &LevelWalls, start: IVec2, goal: IVec2) {
+        self.goal = Some(goal);
+        self.current_waypoint = 0;
+        self.waypoints = find_path(walls, start, goal).unwrap_or_default();
+    }
+
+    fn is_off_path(&self, position: &GridPosition) -> bool {
+        match self.waypoints.get(self.current_waypoint) {
+            None => false,
+            Some(waypoint) => {
+                position
+                    .direction_to(&GridPosition::from_ivec(waypoint))
+                    .length()
+                    > OFF_PATH_DISTANCE
+            }
+        }
+    }
+}
+
+fn grid_square(position: &GridPosition) -> IVec2 {
+    IVec2::new(position.coordinates.x as i32, position.coordinates.y as i32)
+}
+
+/// Finds the cheapest path between two grid squares using A*, moving in 8 directions.
+/// Diagonal moves aren't allowed to cut wall corners, since movement would get stuck on them.
+///
+/// The returned path excludes `start` and includes `goal`. Returns `None` if `goal` can't be reached.
+pub fn find_path(walls: &LevelWalls, start: IVec2, goal: IVec2) -> Option<Vec<IVec2>> {
+    if walls.collides(goal.x, goal.y) {
+        return None;
+    }
+    if start == goal {
+        return Some(vec![]);
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<IVec2, IVec2> = HashMap::new();
+    let mut cost_so_far: HashMap<IVec2, u32> = HashMap::new();
+
+    open.push(OpenNode {
+        square: start,
+        estimated_total_cost: heuristic(start, goal),
+    });
+    cost_so_far.insert(start, 0);
+
+    while let Some(OpenNode { square, .. }) = open.pop() {
+        if square == goal {
+            return Some(reconstruct_path(&came_from, start, goal));
+        }
+
+        let current_cost = cost_so_far[&square];
+        for (neighbor, step_cost) in walkable_neighbors(walls, square) {
+            let new_cost = current_cost + step_cost;
+            if cost_so_far
+                .get(&neighbor)
+                .is_some_and(|&known_cost| known_cost <= new_cost)
+            {
+                continue;
+            }
+            cost_so_far.insert(neighbor, new_cost);
+            came_from.insert(neighbor, square);
+            open.push(OpenNode {
+                square: neighbor,
+                estimated_total_cost: new_cost + heuristic(neighbor, goal),
+            });
+        }
+    }
+
+    None
+}
+
+/// Octile distance, which never overestimates the cost of an 8-directional move
+fn heuristic(from: IVec2, to: IVec2) -> u32 {
+    let delta = (to - from).abs();
+    let diagonal = delta.x.min(delta.y) as u32;
+    let straight = delta.x.max(delta.y) as u32 - diagonal;
+    diagonal * DIAGONAL_COST + straight * ORTHOGONAL_COST
+}
+
+fn walkable_neighbors(walls: &LevelWalls, square: IVec2) -> Vec<(IVec2, u32)> {
+    let mut neighbors = Vec::with_capacity(8);
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let neighbor = square + IVec2::new(dx, dy);
+            if walls.collides(neighbor.x, neighbor.y) {
+                continue;
+            }
+            if dx != 0 && dy != 0 {
+                // don't cut corners
+                if walls.collides(square.x + dx, square.y)
+                    || walls.collides(square.x, square.y + dy)
+                {
+                    continue;
+                }
+                neighbors.push((neighbor, DIAGONAL_COST));
+            } else {
+                neighbors.push((neighbor, ORTHOGONAL_COST));
+            }
+        }
+    }
+    neighbors
+}
+
+fn reconstruct_path(came_from: &HashMap<IVec2, IVec2>, start: IVec2, goal: IVec2) -> Vec<IVec2> {
+    let mut path = vec![goal];
+    let mut current = goal;
+    while let Some(&previous) = came_from.get(&current) {
+        if previous == start {
+            break;
+        }
+        path.push(previous);
+        current = previous;
+    }
+    path.reverse();
+    path
+}
+
+/// Entry in the A* open set. Ordered so that `BinaryHeap` pops the cheapest node first.
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct OpenNode {
+    square: IVec2,
+    estimated_total_cost: u32,
+}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimated_total_cost
+            .cmp(&self.estimated_total_cost)
+            .then_with(|| (self.square.x, self.square.y).cmp(&(other.square.x, other.square.y)))
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Doors opening and closing change which squares are walkable, so every cached path might be stale
+fn invalidate_paths_when_walls_change(
+    walls: Res<LevelWalls>,
+    mut paths: Query<&mut NavigationPath>,
+) {
+    if !walls.is_changed() {
+        return;
+    }
+    for mut path in &mut paths {
+        path.invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use bevy_ecs_ldtk::GridCoords;
+
+    use super::*;
+
+    fn walls(width: i32, height: i32, wall_locations: &[(i32, i32)]) -> LevelWalls {
+        LevelWalls {
+            wall_locations: HashSet::from_iter(
+                wall_locations.iter().map(|&(x, y)| GridCoords::new(x, y)),
+            ),
+            level_width: width,
+            level_height: height,
+        }
+    }
+
+    #[test]
+    fn straight_line_in_open_room() {
+        let walls = walls(5, 5, &[]);
+
+        let path = find_path(&walls, IVec2::new(0, 2), IVec2::new(4, 2)).unwrap();
+
+        assert_eq!(
+            path,
+            vec![
+                IVec2::new(1, 2),
+                IVec2::new(2, 2),
+                IVec2::new(3, 2),
+                IVec2::new(4, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn goes_around_wall() {
+        // a wall down the middle with a gap at the top
+        let walls = walls(5, 5, &[(2, 0), (2, 1), (2, 2), (2, 3)]);
+
+        let path = find_path(&walls, IVec2::new(0, 0), IVec2::new(4, 0)).unwrap();
+
+        assert!(path.contains(&IVec2::new(2, 4)), "{:?}", path);
+        assert!(path
+            .iter()
+            .all(|square| !walls.collides(square.x, square.y)));
+        assert_eq!(path.last(), Some(&IVec2::new(4, 0)));
+    }
+
+    #[test]
+    fn does_not_cut_corners() {
+        let walls = walls(3, 3, &[(1, 0)]);
+
+        let path = find_path(&walls, IVec2::new(0, 0), IVec2::new(2, 1)).unwrap();
+
+        assert_eq!(path.first(), Some(&IVec2::new(0, 1)), "{:?}", path);
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let walls = walls(5, 5, &[(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)]);
+
+        assert_eq!(find_path(&walls, IVec2::new(0, 0), IVec2::new(4, 0)), None);
+        assert_eq!(find_path(&walls, IVec2::new(0, 0), IVec2::new(2, 2)), None);
+    }
+
+    #[test]
+    fn steering_follows_path_and_replans_on_new_goal() {
+        let walls = walls(5, 5, &[(1, 0), (1, 1), (1, 2), (1, 3)]);
+        let mut path = NavigationPath::default();
+
+        let direction = path.steer(
+            &walls,
+            &GridPosition::new(0., 0.),
+            &GridPosition::new(2., 0.),
+        );
+        assert_eq!(direction, Vec2::new(0., 1.));
+
+        let direction = path.steer(
+            &walls,
+            &GridPosition::new(0., 0.),
+            &GridPosition::new(0., 2.),
+        );
+        assert_eq!(direction, Vec2::new(0., 1.));
+        assert_eq!(path.goal, Some(IVec2::new(0, 2)));
+    }
+}
@@ -0,0 +1,131 @@
+//! Hunters that lose sight of their prey walk over to where they last saw it and look around for a while,
+//! before giving up and returning to their post.
+
+use std::f32::consts;
+
+use bevy::prelude::*;
+
+use crate::game::ai::pathfinding::NavigationPath;
+use crate::game::ai::{main_ai_behavior_system, AiState, HasAiState};
+use crate::game::grid::GridPosition;
+use crate::game::line_of_sight::vision::Facing;
+use crate::game::movement::GridMovement;
+use crate::game::spawn::enemy::{CanSeePlayer, ENEMY_SEARCH_SPEED};
+use crate::game::spawn::level::LevelWalls;
+use crate::screen::Screen;
+use crate::AppSet;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(SearchSettings {
+        search_seconds: 6.0,
+        sweep_half_angle_radians: consts::FRAC_PI_2,
+        sweep_seconds: 3.0,
+        give_up_travelling_after_seconds: 10.0,
+    });
+
+    // systems
+    app.add_systems(
+        FixedUpdate,
+        search_for_player
+            .after(main_ai_behavior_system)
+            .run_if(in_state(Screen::Playing))
+            .in_set(AppSet::UpdateAi),
+    );
+
+    // reflection
+    app.register_type::<SearchSettings>();
+    app.register_type::<SearchState>();
+}
+
+/// Tunables for how long and how thoroughly hunters search for the player
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct SearchSettings {
+    /// How long to look around once we've reached the last known position
+    pub search_seconds: f32,
+    /// How far to either side of the original facing we sweep while looking around
+    pub sweep_half_angle_radians: f32,
+    /// How long one full left-right-left sweep takes
+    pub sweep_seconds: f32,
+    /// If we can't reach the last known position in this long, start looking around wherever we are
+    pub give_up_travelling_after_seconds: f32,
+}
+
+/// Where a hunter last saw the player, and how far along it is in searching for them.
+#[derive(Component, Reflect, Debug, Clone, Default)]
+#[reflect(Component)]
+pub struct SearchState {
+    pub last_known_position: GridPosition,
+    timer: Timer,
+    has_arrived: bool,
+    sweep_center: Vec2,
+}
+
+impl SearchState {
+    /// Start a new search at the given position, throwing away any previous search
+    pub fn begin(&mut self, last_known_position: GridPosition, settings: &SearchSettings) {
+        self.last_known_position = last_known_position;
+        self.has_arrived = false;
+        self.timer =
+            Timer::from_seconds(settings.give_up_travelling_after_seconds, TimerMode::Once);
+    }
+
+    fn arrive(&mut self, facing: Vec2, settings: &SearchSettings) {
+        self.has_arrived = true;
+        self.sweep_center = facing;
+        self.timer = Timer::from_seconds(settings.search_seconds, TimerMode::Once);
+    }
+}
+
+fn search_for_player(
+    mut query: Query<
+        (
+            &mut HasAiState,
+            &mut SearchState,
+            &GridPosition,
+            &mut Facing,
+            &mut GridMovement,
+            &mut NavigationPath,
+        ),
+        Without<CanSeePlayer>,
+    >,
+    settings: Res<SearchSettings>,
+    walls: Res<LevelWalls>,
+    time: Res<Time>,
+) {
+    for (mut ai, mut search, position, mut facing, mut movement, mut path) in &mut query {
+        if ai.current_state != AiState::Searching {
+            continue;
+        }
+
+        search.timer.tick(time.delta());
+
+        if !search.has_arrived {
+            let target = search.last_known_position;
+            let close_enough = position.direction_to(&target).length() < 0.5;
+            if close_enough || search.timer.finished() {
+                movement.acceleration_player_force = Vec2::ZERO;
+                path.invalidate();
+                search.arrive(facing.0, &settings);
+            } else {
+                let direction = path.steer(&walls, position, &target);
+                if direction != Vec2::ZERO {
+                    facing.0 = direction;
+                }
+                movement.acceleration_player_force =
+                    direction.normalize_or_zero() * ENEMY_SEARCH_SPEED;
+            }
+            continue;
+        }
+
+        if search.timer.finished() {
+            ai.transition_to(AiState::ReturningToPost);
+            continue;
+        }
+
+        // look left and right around the direction we were facing when we got here
+        let sweep_progress = search.timer.elapsed_secs() / settings.sweep_seconds;
+        let angle = (sweep_progress * 2.0 * consts::PI).sin() * settings.sweep_half_angle_radians;
+        facing.0 = Vec2::from_angle(angle).rotate(search.sweep_center);
+    }
+}
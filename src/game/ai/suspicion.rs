@@ -0,0 +1,324 @@
+//! Hunters don't spot the player instantly. Instead, a suspicion meter fills up while they can see the player,
+//! faster when the player is close, right in front of them, or moving around. Once it's full, they give chase.
+
+use bevy::prelude::*;
+
+use crate::game::ai::{main_ai_behavior_system, AiState, HasAiState};
+use crate::game::grid::GridPosition;
+use crate::game::line_of_sight::vision::{Facing, VisionAbility, VisionArchetype};
+use crate::game::movement::GridMovement;
+use crate::game::spawn::enemy::CanSeePlayer;
+use crate::screen::Screen;
+use crate::AppSet;
+
+pub fn plugin(app: &mut App) {
+    // systems
+    app.add_systems(
+        FixedUpdate,
+        investigate_suspicion
+            .after(main_ai_behavior_system)
+            .run_if(in_state(Screen::Playing))
+            .in_set(AppSet::UpdateAi),
+    );
+    app.add_systems(
+        Update,
+        update_suspicion_meters
+            .run_if(in_state(Screen::Playing))
+            .in_set(AppSet::UpdateWorld),
+    );
+    app.observe(attach_suspicion_meter);
+
+    // reflection
+    app.register_type::<Suspicion>();
+}
+
+/// Once suspicion gets this high, the hunter stops what it's doing and stares at the player
+pub const SUSPICIOUS_THRESHOLD: f32 = 0.25;
+
+/// Hunters that are already searching for the player are on edge and notice them faster
+pub const SEARCHING_FILL_MULTIPLIER: f32 = 2.0;
+
+/// How fast suspicion drains away when the player isn't visible, per second
+const SUSPICION_DECAY_PER_SECOND: f32 = 0.25;
+
+/// How much slower suspicion fills at the very edge of our vision range, compared to right next to us
+const MAX_DISTANCE_PENALTY: f32 = 0.7;
+
+/// How much slower suspicion fills at the edge of our field of view, compared to straight ahead
+const MAX_ANGLE_PENALTY: f32 = 0.5;
+
+/// A ghost can make hunters suspicious, but never sure enough to raise the alarm
+pub const DECOY_SUSPICION_CAP: f32 = 0.6;
+
+/// Players standing still are harder to notice, rolling ones are easier
+const STILL_PLAYER_MULTIPLIER: f32 = 0.5;
+const ROLLING_PLAYER_MULTIPLIER: f32 = 1.5;
+
+/// How alert a hunter is to the player, from 0 (oblivious) to 1 (spotted).
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+#[reflect(Component)]
+pub struct Suspicion {
+    pub value: f32,
+    /// How long it takes to fill the meter under ideal conditions
+    pub seconds_to_detect: f32,
+    /// Where the player was when we last noticed them
+    pub last_noticed_position: GridPosition,
+}
+
+impl Default for Suspicion {
+    fn default() -> Self {
+        Self::of(VisionArchetype::default())
+    }
+}
+
+impl Suspicion {
+    pub fn of(archetype: VisionArchetype) -> Self {
+        let seconds_to_detect = match archetype {
+            VisionArchetype::Sniper => 0.5,
+            VisionArchetype::Patrol => 1.2,
+            VisionArchetype::Ghost | VisionArchetype::Player => 1.0,
+        };
+        Self {
+            value: 0.0,
+            seconds_to_detect,
+            last_noticed_position: GridPosition::default(),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.value >= 1.0
+    }
+
+    pub fn is_suspicious(&self) -> bool {
+        self.value >= SUSPICIOUS_THRESHOLD
+    }
+
+    /// We can see the player, so get more suspicious based on how obvious they are
+    pub fn notice(&mut self, player_position: GridPosition, fill_rate: f32, delta_seconds: f32) {
+        self.last_noticed_position = player_position;
+        self.value = (self.value + fill_rate * delta_seconds).min(1.0);
+    }
+
+    /// We can see a ghost and think it might be the player. Unless we're already more suspicious of something else,
+    /// look at the ghost, and get more suspicious up to [`DECOY_SUSPICION_CAP`].
+    pub fn notice_decoy(&mut self, decoy_position: GridPosition, delta_seconds: f32) {
+        if self.value >= DECOY_SUSPICION_CAP {
+            return;
+        }
+        self.last_noticed_position = decoy_position;
+        self.value = (self.value + delta_seconds / self.seconds_to_detect).min(DECOY_SUSPICION_CAP);
+    }
+
+    /// We can't see the player, so slowly calm down
+    pub fn decay(&mut self, delta_seconds: f32) {
+        self.value = (self.value - SUSPICION_DECAY_PER_SECOND * delta_seconds).max(0.0);
+    }
+
+    /// How much the meter fills per second while we can see the player
+    pub fn fill_rate(
+        &self,
+        hunter_position: &GridPosition,
+        hunter_facing: &Facing,
+        vision: &VisionAbility,
+        player_position: &GridPosition,
+        player_movement: &GridMovement,
+    ) -> f32 {
+        let to_player = hunter_position.direction_to(player_position);
+
+        let distance_fraction = (to_player.length() / vision.range_in_grid_units).clamp(0.0, 1.0);
+        let distance_multiplier = 1.0 - MAX_DISTANCE_PENALTY * distance_fraction;
+
+        let angle_fraction = if to_player == Vec2::ZERO || hunter_facing.0 == Vec2::ZERO {
+            0.0
+        } else {
+            (hunter_facing.0.angle_between(to_player).abs() / vision.field_of_view_radians)
+                .clamp(0.0, 1.0)
+        };
+        let angle_multiplier = 1.0 - MAX_ANGLE_PENALTY * angle_fraction;
+
+        let movement_multiplier = if player_movement.is_rolling {
+            ROLLING_PLAYER_MULTIPLIER
+        } else if player_movement.velocity == Vec2::ZERO {
+            STILL_PLAYER_MULTIPLIER
+        } else {
+            1.0
+        };
+
+        distance_multiplier * angle_multiplier * movement_multiplier / self.seconds_to_detect
+    }
+}
+
+/// Suspicious hunters stop in their tracks and stare at where they noticed something
+fn investigate_suspicion(
+    mut query: Query<
+        (
+            &HasAiState,
+            &Suspicion,
+            &GridPosition,
+            &mut Facing,
+            &mut GridMovement,
+        ),
+        Without<CanSeePlayer>,
+    >,
+) {
+    for (ai, suspicion, position, mut facing, mut movement) in &mut query {
+        if ai.current_state != AiState::Suspicious {
+            continue;
+        }
+        movement.acceleration_player_force = Vec2::ZERO;
+        let direction = position.direction_to(&suspicion.last_noticed_position);
+        if direction != Vec2::ZERO {
+            facing.0 = direction;
+        }
+    }
+}
+
+/// A little bar floating above a hunter's head that shows how close it is to spotting the player
+#[derive(Component)]
+struct SuspicionMeter;
+
+const SUSPICION_METER_SIZE: Vec2 = Vec2::new(12.0, 2.0);
+const SUSPICION_METER_OFFSET: Vec3 = Vec3::new(0.0, 11.0, 1.0);
+
+fn attach_suspicion_meter(trigger: Trigger<OnAdd, Suspicion>, mut commands: Commands) {
+    commands.entity(trigger.entity()).with_children(|parent| {
+        parent.spawn((
+            Name::new("SuspicionMeter"),
+            SuspicionMeter,
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(SUSPICION_METER_SIZE),
+                    ..default()
+                },
+                transform: Transform::from_translation(SUSPICION_METER_OFFSET),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    });
+}
+
+fn update_suspicion_meters(
+    suspicions: Query<&Suspicion>,
+    mut meters: Query<
+        (&Parent, &mut Sprite, &mut Transform, &mut Visibility),
+        With<SuspicionMeter>,
+    >,
+) {
+    for (parent, mut sprite, mut transform, mut visibility) in &mut meters {
+        let Ok(suspicion) = suspicions.get(parent.get()) else {
+            continue;
+        };
+        if suspicion.value <= 0.0 {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+
+        // grow from the left edge, going from yellow to red as it fills
+        transform.scale.x = suspicion.value;
+        transform.translation.x = -SUSPICION_METER_SIZE.x * (1.0 - suspicion.value) / 2.0;
+        sprite.color = Color::srgb(1.0, 1.0 - suspicion.value, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts;
+
+    use super::*;
+
+    fn rate_for(player_position: GridPosition, player_movement: GridMovement) -> f32 {
+        let suspicion = Suspicion::of(VisionArchetype::Patrol);
+        suspicion.fill_rate(
+            &GridPosition::new(0., 0.),
+            &Facing(Vec2::new(1., 0.)),
+            &VisionAbility::of(VisionArchetype::Patrol),
+            &player_position,
+            &player_movement,
+        )
+    }
+
+    fn walking() -> GridMovement {
+        GridMovement {
+            velocity: Vec2::new(0.1, 0.),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn closer_players_are_noticed_faster() {
+        let near = rate_for(GridPosition::new(1., 0.), walking());
+        let far = rate_for(GridPosition::new(4., 0.), walking());
+        assert!(near > far, "{} <= {}", near, far);
+    }
+
+    #[test]
+    fn players_in_front_are_noticed_faster() {
+        let ahead = rate_for(GridPosition::new(3., 0.), walking());
+        let to_the_side = rate_for(GridPosition::new(3., 2.), walking());
+        assert!(ahead > to_the_side, "{} <= {}", ahead, to_the_side);
+    }
+
+    #[test]
+    fn still_players_are_noticed_slower() {
+        let moving = rate_for(GridPosition::new(3., 0.), walking());
+        let still = rate_for(GridPosition::new(3., 0.), GridMovement::default());
+        assert!(moving > still, "{} <= {}", moving, still);
+    }
+
+    #[test]
+    fn snipers_notice_faster_than_patrols() {
+        let sniper = Suspicion::of(VisionArchetype::Sniper);
+        let patrol = Suspicion::of(VisionArchetype::Patrol);
+        let vision = VisionAbility {
+            field_of_view_radians: consts::FRAC_PI_4,
+            range_in_grid_units: 5.0,
+        };
+        let args = (
+            GridPosition::new(0., 0.),
+            Facing(Vec2::new(1., 0.)),
+            GridPosition::new(3., 0.),
+            walking(),
+        );
+        assert!(
+            sniper.fill_rate(&args.0, &args.1, &vision, &args.2, &args.3)
+                > patrol.fill_rate(&args.0, &args.1, &vision, &args.2, &args.3)
+        );
+    }
+
+    #[test]
+    fn meter_fills_and_drains() {
+        let mut suspicion = Suspicion::of(VisionArchetype::Patrol);
+        suspicion.notice(GridPosition::new(1., 1.), 2.0, 0.2);
+        assert!(suspicion.is_suspicious());
+        assert!(!suspicion.is_full());
+
+        suspicion.notice(GridPosition::new(1., 1.), 2.0, 1.0);
+        assert!(suspicion.is_full());
+        assert_eq!(suspicion.value, 1.0);
+
+        suspicion.decay(10.0);
+        assert_eq!(suspicion.value, 0.0);
+    }
+
+    #[test]
+    fn decoys_never_fill_the_meter() {
+        let mut suspicion = Suspicion::of(VisionArchetype::Patrol);
+        suspicion.notice_decoy(GridPosition::new(2., 0.), 10.0);
+
+        assert!(suspicion.is_suspicious());
+        assert!(!suspicion.is_full());
+        assert_eq!(suspicion.last_noticed_position, GridPosition::new(2., 0.));
+    }
+
+    #[test]
+    fn decoys_dont_distract_from_something_more_suspicious() {
+        let mut suspicion = Suspicion::of(VisionArchetype::Patrol);
+        suspicion.notice(GridPosition::new(1., 1.), 1.0, 0.9);
+        suspicion.notice_decoy(GridPosition::new(2., 0.), 1.0);
+
+        assert_eq!(suspicion.value, 0.9);
+        assert_eq!(suspicion.last_noticed_position, GridPosition::new(1., 1.));
+    }
+}
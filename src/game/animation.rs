@@ -23,16 +23,16 @@ pub(super) fn plugin(app: &mut App) {
         Update,
         (
             update_animation_timer.in_set(AppSet::TickTimers),
-            (
-                update_animation_movement,
-                update_animation_atlas,
-                trigger_step_sfx,
-                trigger_roll_sfx,
-            )
+            (update_animation_atlas, trigger_step_sfx, trigger_roll_sfx)
                 .chain()
                 .in_set(AppSet::Update),
         ),
     );
+    // ghosts record the animation state, so it's worked out on the same ticks as movement
+    app.add_systems(
+        FixedUpdate,
+        update_animation_movement.in_set(AppSet::Update),
+    );
     app.register_type::<PlayerAnimation>();
     app.observe(reset_on_death);
 }
@@ -1,12 +1,20 @@
+use bevy::audio::Volume;
 use bevy::prelude::*;
 
 use sfx::Sfx;
+use soundtrack::SoundtrackMarker;
+
+use crate::settings::Settings;
 
 pub mod sfx;
 pub mod soundtrack;
 
 pub fn plugin(app: &mut App) {
     app.add_systems(Update, button_interaction_sfx);
+    app.add_systems(
+        Update,
+        apply_volume_settings.run_if(resource_changed::<Settings>),
+    );
 
     app.observe(soundtrack::play_soundtrack);
     app.observe(sfx::play_sfx);
@@ -25,3 +33,15 @@ fn button_interaction_sfx(
         }
     }
 }
+
+/// New sounds pick up the global volume when they start, but music that's already playing needs turning up or down
+fn apply_volume_settings(
+    settings: Res<Settings>,
+    mut global_volume: ResMut<GlobalVolume>,
+    soundtracks: Query<&AudioSink, With<SoundtrackMarker>>,
+) {
+    global_volume.volume = Volume::new(settings.global_volume());
+    for sink in &soundtracks {
+        sink.set_volume(settings.global_volume() * settings.music_volume);
+    }
+}
@@ -1,14 +1,26 @@
-use bevy::{audio::PlaybackMode, prelude::*};
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+};
 use rand::prelude::SliceRandom;
+use rand::Rng;
 
 use crate::game::assets::{SfxAsset, SfxAssets};
+use crate::game::rng::{GameRng, RngStream};
+use crate::settings::Settings;
 
-pub(super) fn play_sfx(trigger: Trigger<Sfx>, mut commands: Commands, sfxs: Res<SfxAssets>) {
+pub(super) fn play_sfx(
+    trigger: Trigger<Sfx>,
+    mut commands: Commands,
+    sfxs: Res<SfxAssets>,
+    audio_settings: Res<Settings>,
+    mut rng: ResMut<GameRng>,
+) {
     let event = trigger.event();
     let source = match event {
         Sfx::ButtonHover => &sfxs[&SfxAsset::ButtonHover],
         Sfx::ButtonPress => &sfxs[&SfxAsset::ButtonPress],
-        Sfx::Step => random_step(&sfxs),
+        Sfx::Step => random_step(&sfxs, rng.stream(RngStream::Sfx)),
         Sfx::Roll => &sfxs[&SfxAsset::Roll],
         Sfx::Death => &sfxs[&SfxAsset::Death],
         Sfx::Detected => &sfxs[&SfxAsset::Detected],
@@ -21,6 +33,7 @@ pub(super) fn play_sfx(trigger: Trigger<Sfx>, mut commands: Commands, sfxs: Res<
     .clone_weak();
     let settings = PlaybackSettings {
         mode: PlaybackMode::Despawn,
+        volume: Volume::new(audio_settings.sfx_volume),
         ..default()
     };
     commands.spawn(AudioSourceBundle { source, settings });
@@ -43,13 +56,13 @@ pub enum Sfx {
     Win,
 }
 
-fn random_step(sfxs: &SfxAssets) -> &Handle<AudioSource> {
+fn random_step<'a>(sfxs: &'a SfxAssets, rng: &mut impl Rng) -> &'a Handle<AudioSource> {
     [
         &sfxs[&SfxAsset::Step1],
         &sfxs[&SfxAsset::Step2],
         &sfxs[&SfxAsset::Step3],
         &sfxs[&SfxAsset::Step4],
     ]
-    .choose(&mut rand::thread_rng())
+    .choose(rng)
     .unwrap()
 }
@@ -1,12 +1,17 @@
-use bevy::{audio::PlaybackMode, prelude::*};
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+};
 
 use crate::game::assets::{SoundtrackAsset, SoundtrackAssets};
 use crate::game::spawn::health::OnDeath;
+use crate::settings::Settings;
 
 pub(super) fn play_soundtrack(
     trigger: Trigger<Soundtrack>,
     mut commands: Commands,
     soundtracks: Res<SoundtrackAssets>,
+    audio_settings: Res<Settings>,
     query: Query<Entity, With<SoundtrackMarker>>,
 ) {
     let event = trigger.event();
@@ -25,6 +30,7 @@ pub(super) fn play_soundtrack(
 
     let settings = PlaybackSettings {
         mode: PlaybackMode::Loop,
+        volume: Volume::new(audio_settings.music_volume),
         ..default()
     };
     commands.spawn((AudioSourceBundle { source, settings }, SoundtrackMarker));
@@ -4,8 +4,10 @@ use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
 use leafwing_input_manager::action_state::ActionState;
 
+use crate::game::grid::grid_layout::GridLayout;
 use crate::game::spawn::player::Player;
 use crate::input::PlayerAction;
+use crate::settings::Settings;
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<CameraProperties>();
@@ -14,12 +16,17 @@ pub(super) fn plugin(app: &mut App) {
         Update,
         (
             record_binary_zoom_input,
+            record_button_zoom_input,
             record_smooth_zoom_input,
             apply_camera_zoom,
             camera_follow,
         )
             .chain(),
     );
+    app.add_systems(
+        Update,
+        apply_camera_settings.run_if(resource_changed::<Settings>),
+    );
 }
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
@@ -30,29 +37,27 @@ pub struct CameraFollowTarget;
 #[reflect(Component)]
 pub struct CanZoomSmoothly(f32);
 
-fn spawn_camera(mut commands: Commands) {
+fn spawn_camera(mut commands: Commands, settings: Res<Settings>) {
     let mut camera = Camera2dBundle::default();
-    camera.projection.scale = INITIAL_CAMERA_ZOOM;
+    camera.projection.scale = settings.default_zoom;
     commands.spawn((
         Name::new("Camera"),
         camera,
         IsDefaultUiCamera,
         CameraProperties {
-            initial_camera_zoom: INITIAL_CAMERA_ZOOM,
+            initial_camera_zoom: settings.default_zoom,
             camera_zoom_snappiness: 20.0,
             zoom_sensitivity: 1.0,
             mouse_wheel_sensitivity_multiplier: 5.0,
             camera_zoom_max: 0.7,
             camera_zoom_min: 0.1,
             camera_zoom_buffer: 0.01,
-            camera_follow_snappiness: 7.0,
+            camera_follow_snappiness: settings.follow_snappiness,
         },
-        CanZoomSmoothly(INITIAL_CAMERA_ZOOM),
+        CanZoomSmoothly(settings.default_zoom),
     ));
 }
 
-const INITIAL_CAMERA_ZOOM: f32 = 0.23;
-
 #[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
 pub struct CameraProperties {
     initial_camera_zoom: f32,
@@ -110,6 +115,33 @@ fn record_binary_zoom_input(
     }
 }
 
+fn record_button_zoom_input(
+    time: Res<Time>,
+    action: Query<&ActionState<PlayerAction>>,
+    mut query: Query<(&mut CanZoomSmoothly, &CameraProperties), With<Camera>>,
+) {
+    if let Ok((mut zoom_destination, camera_properties)) = query.get_single_mut() {
+        for act in action.iter() {
+            let mut direction = 0.0;
+            if act.pressed(&PlayerAction::ZoomIn) {
+                direction -= 1.0;
+            }
+            if act.pressed(&PlayerAction::ZoomOut) {
+                direction += 1.0;
+            }
+            if direction == 0.0 {
+                continue;
+            }
+            let dist = camera_properties.zoom_sensitivity * time.delta_seconds();
+            let log_scale = zoom_destination.0.ln() + direction * dist;
+            zoom_destination.0 = log_scale.exp().clamp(
+                camera_properties.camera_zoom_min,
+                camera_properties.camera_zoom_max,
+            );
+        }
+    }
+}
+
 fn apply_camera_zoom(
     time: Res<Time>,
     mut query: Query<
@@ -137,12 +169,24 @@ fn apply_camera_zoom(
     }
 }
 
+fn apply_camera_settings(
+    settings: Res<Settings>,
+    mut query: Query<(&mut CameraProperties, &mut CanZoomSmoothly), With<Camera>>,
+) {
+    for (mut properties, mut zoom_destination) in &mut query {
+        properties.initial_camera_zoom = settings.default_zoom;
+        properties.camera_follow_snappiness = settings.follow_snappiness;
+        zoom_destination.0 = settings.default_zoom;
+    }
+}
+
 fn camera_follow(
     mut camera: Query<
         (&mut Transform, &OrthographicProjection, &CameraProperties),
         (With<Camera>, Without<Player>),
     >,
     target: Query<&Transform, (With<CameraFollowTarget>, Without<Camera>)>,
+    grid: Res<GridLayout>,
     time: Res<Time>,
 ) {
     let Ok(target_transform) = target.get_single() else {
@@ -151,15 +195,12 @@ fn camera_follow(
     let Ok((mut camera_transform, orthographic, properties)) = camera.get_single_mut() else {
         return;
     };
-    //calculate bounds
-    let vertical_bounds = orthographic.area.width();
-    let horizontal_bounds = orthographic.area.height();
-    let level_width = 2048.0;
-    let level_height = 2048.0;
-    let min_x = (horizontal_bounds / 2.0).min(level_width / 2.0);
-    let max_x = (level_width - horizontal_bounds / 2.0).max(level_width / 2.0);
-    let min_y = (vertical_bounds / 2.0).min(level_height / 2.0);
-    let max_y = (level_height - vertical_bounds / 2.0).max(level_height / 2.0);
+    //calculate bounds, keeping the camera centered on levels smaller than the screen
+    let level = grid.world_bounds();
+    let half_view = orthographic.area.size() / 2.0;
+    let min = (level.min + half_view).min(level.center());
+    let max = (level.max - half_view).max(level.center());
+    let (min_x, min_y, max_x, max_y) = (min.x, min.y, max.x, max.y);
 
     let bounded_target_position = Vec3::new(
         target_transform.translation.x.clamp(min_x, max_x),
@@ -0,0 +1,225 @@
+//! The game is a campaign of LDtk levels, played in the order they're laid out in the project.
+//! Reaching the goal moves on to the next level, and reaching the goal of the final level wins the game.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_ecs_ldtk::prelude::RawLevelAccessor;
+use bevy_ecs_ldtk::{LevelEvent, LevelIid, LevelSelection, Respawn};
+
+use crate::game::end_game::EndGameCondition;
+use crate::game::threat::PlayStopwatch;
+use crate::screen::Screen;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Campaign>();
+    app.insert_resource(LevelSelection::index(0));
+
+    // systems
+    app.add_systems(OnEnter(Screen::Playing), start_campaign);
+    app.add_systems(Update, (count_campaign_levels, finish_changing_level));
+    app.observe(advance_campaign);
+    app.observe(restart_level);
+    app.observe(log_level_started);
+
+    // reflection
+    app.register_type::<Campaign>();
+}
+
+/// Triggered when the player reaches the goal of the current level
+#[derive(Event, Debug)]
+pub struct LevelCompleted;
+
+/// Triggered when the player starts a level, either at the start of the campaign or after finishing the previous one.
+/// Anything that only makes sense within a single level (ghosts, keys, threat, fog) resets itself when this happens.
+#[derive(Event, Debug)]
+pub struct LevelStarted(pub usize);
+
+/// Trigger this to play the current level again from the start, keeping the campaign's progress so far
+#[derive(Event, Debug)]
+pub struct RestartLevel;
+
+#[derive(Resource, Reflect, Debug, Default)]
+#[reflect(Resource)]
+pub struct Campaign {
+    /// Index of the level being played, in LDtk's level order
+    pub current_level: usize,
+    pub level_count: usize,
+    /// How long each completed level took
+    pub splits: Vec<Duration>,
+    /// Set from completing a level until the next one has spawned, so the same goal can't be reached twice
+    changing_level: bool,
+}
+
+impl Campaign {
+    pub fn is_final_level(&self) -> bool {
+        self.current_level + 1 >= self.level_count
+    }
+
+    pub fn is_changing_level(&self) -> bool {
+        self.changing_level
+    }
+
+    fn restart(&mut self) {
+        self.current_level = 0;
+        self.splits.clear();
+        self.changing_level = false;
+    }
+
+    /// Records the split for the current level, given how long the whole campaign has taken so far.
+    /// Returns the index of the level to play next, or `None` if that was the final level.
+    fn complete_level(&mut self, total_elapsed: Duration) -> Option<usize> {
+        let previous_levels: Duration = self.splits.iter().sum();
+        self.splits
+            .push(total_elapsed.saturating_sub(previous_levels));
+        self.changing_level = true;
+
+        if self.is_final_level() {
+            None
+        } else {
+            self.current_level += 1;
+            Some(self.current_level)
+        }
+    }
+}
+
+fn start_campaign(
+    mut campaign: ResMut<Campaign>,
+    mut stopwatch: ResMut<PlayStopwatch>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut commands: Commands,
+) {
+    campaign.restart();
+    stopwatch.0.reset();
+    *level_selection = LevelSelection::index(0);
+    commands.trigger(LevelStarted(0));
+}
+
+fn advance_campaign(
+    _trigger: Trigger<LevelCompleted>,
+    mut campaign: ResMut<Campaign>,
+    stopwatch: Res<PlayStopwatch>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut commands: Commands,
+) {
+    if campaign.is_changing_level() {
+        return;
+    }
+
+    match campaign.complete_level(stopwatch.0.elapsed()) {
+        Some(next_level) => {
+            info!("Moving on to level {}", next_level);
+            *level_selection = LevelSelection::index(next_level);
+            commands.trigger(LevelStarted(next_level));
+        }
+        None => commands.trigger(EndGameCondition::Win),
+    }
+}
+
+/// Respawns the current level from LDtk, so every enemy, key and door is back where it started
+fn restart_level(
+    _trigger: Trigger<RestartLevel>,
+    mut campaign: ResMut<Campaign>,
+    mut stopwatch: ResMut<PlayStopwatch>,
+    levels: Query<Entity, With<LevelIid>>,
+    mut commands: Commands,
+) {
+    if campaign.is_changing_level() {
+        return;
+    }
+
+    info!("Restarting level {}", campaign.current_level);
+    campaign.changing_level = true;
+    stopwatch.0.set_elapsed(campaign.splits.iter().sum());
+    for level in &levels {
+        commands.entity(level).insert(Respawn);
+    }
+    commands.trigger(LevelStarted(campaign.current_level));
+}
+
+fn log_level_started(trigger: Trigger<LevelStarted>) {
+    info!("Started level {}", trigger.event().0);
+}
+
+fn count_campaign_levels(
+    mut asset_events: EventReader<AssetEvent<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut campaign: ResMut<Campaign>,
+) {
+    for asset_event in asset_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = asset_event
+        else {
+            continue;
+        };
+        if let Some(ldtk_project) = ldtk_project_assets.get(*id) {
+            campaign.level_count = ldtk_project.iter_raw_levels().count();
+        }
+    }
+}
+
+fn finish_changing_level(
+    mut level_events: EventReader<LevelEvent>,
+    mut campaign: ResMut<Campaign>,
+) {
+    if level_events
+        .read()
+        .any(|level_event| matches!(level_event, LevelEvent::Spawned(_)))
+    {
+        campaign.changing_level = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campaign_of(level_count: usize) -> Campaign {
+        Campaign {
+            level_count,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn advances_through_levels_in_order() {
+        let mut campaign = campaign_of(3);
+
+        assert_eq!(campaign.complete_level(Duration::from_secs(10)), Some(1));
+        campaign.changing_level = false;
+        assert_eq!(campaign.complete_level(Duration::from_secs(25)), Some(2));
+        campaign.changing_level = false;
+        assert!(campaign.is_final_level());
+        assert_eq!(campaign.complete_level(Duration::from_secs(30)), None);
+    }
+
+    #[test]
+    fn splits_are_per_level() {
+        let mut campaign = campaign_of(3);
+
+        campaign.complete_level(Duration::from_secs(10));
+        campaign.complete_level(Duration::from_secs(25));
+        campaign.complete_level(Duration::from_secs(30));
+
+        assert_eq!(
+            campaign.splits,
+            vec![
+                Duration::from_secs(10),
+                Duration::from_secs(15),
+                Duration::from_secs(5)
+            ]
+        );
+    }
+
+    #[test]
+    fn restarting_forgets_progress() {
+        let mut campaign = campaign_of(2);
+        campaign.complete_level(Duration::from_secs(10));
+
+        campaign.restart();
+
+        assert_eq!(campaign.current_level, 0);
+        assert!(campaign.splits.is_empty());
+        assert!(!campaign.is_changing_level());
+    }
+}
@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 use rand::seq::SliceRandom;
+use rand::Rng;
 use std::collections::HashMap;
+
+use crate::game::rng::{GameRng, RngStream};
+
 const GRID_SIZE: f32 = 16.0;
 const DIALOG_OFFSET: Vec3 = Vec3::new(0.0, GRID_SIZE, 0.0);
 
@@ -35,8 +39,8 @@ impl DialogLines {
     }
 
     /// Picks a line at random from all the lines
-    pub fn random_line(&self) -> String {
-        self.lines.choose(&mut rand::thread_rng()).unwrap().clone()
+    pub fn random_line(&self, rng: &mut impl Rng) -> String {
+        self.lines.choose(rng).unwrap().clone()
     }
 
     /// Get a specific line, by index
@@ -148,6 +152,7 @@ fn handle_show_dialog_event(
     trigger: Trigger<ShowDialogEvent>,
     mut commands: Commands,
     mut dialog_line_resource: ResMut<DialogLineResource>,
+    mut rng: ResMut<GameRng>,
     dialog_query: Query<(Entity, &Parent), With<Dialog>>,
     asset_server: Res<AssetServer>,
 ) {
@@ -181,7 +186,10 @@ fn handle_show_dialog_event(
         }
         ShowDialogType::RandomLine(dialog_type) => {
             if let Some(dialog_lines) = dialog_line_resource.store.get(dialog_type) {
-                Some((dialog_lines.random_line(), default_duration))
+                Some((
+                    dialog_lines.random_line(rng.stream(RngStream::Dialog)),
+                    default_duration,
+                ))
             } else {
                 info!("Can't find dialog lines for {:?}", dialog_type);
                 return;
@@ -1,7 +1,209 @@
+//! The ways a run can end, and the stats we keep along the way to show on the game over screen.
+
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::spawn::health::OnDeath;
+use crate::screen::Screen;
+use crate::settings::Settings;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<LoseConditions>();
+    app.init_resource::<RunStats>();
 
-#[derive(Event)]
-#[allow(dead_code)]
+    // systems
+    app.add_systems(
+        OnEnter(Screen::Playing),
+        (reset_run_stats, apply_lose_conditions),
+    );
+    app.observe(count_deaths);
+    app.observe(count_detections);
+
+    // reflection
+    app.register_type::<LoseConditions>();
+    app.register_type::<RunStats>();
+}
+
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EndGameCondition {
+    /// Reached the goal of the final level
     Win,
+    /// The final threat level ran out
+    TimeOut,
+    /// Died more times than `LoseConditions::max_deaths` allows
+    OutOfLives,
+    /// Got spotted while `LoseConditions::one_detection` is on
+    Caught,
+}
+
+/// Triggered whenever an enemy notices the player
+#[derive(Event, Debug)]
+pub struct PlayerDetected;
+
+/// Which ways of losing are turned on. Players pick them in the settings, and they're fixed for the rest of a run
+/// once it starts.
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct LoseConditions {
+    /// The game ends once the final threat level runs out, instead of lasting forever
+    pub time_out: bool,
+    /// How many times the player can die before it's game over, or `None` for as many as they like
+    pub max_deaths: Option<u32>,
+    /// Getting spotted by any enemy at all ends the game
+    pub one_detection: bool,
+}
+
+/// What happened during the current run
+#[derive(Resource, Reflect, Debug, Default)]
+#[reflect(Resource)]
+pub struct RunStats {
+    pub deaths: u32,
+    pub detections: u32,
+}
+
+impl Default for LoseConditions {
+    fn default() -> Self {
+        Self {
+            time_out: true,
+            max_deaths: None,
+            one_detection: false,
+        }
+    }
+}
+
+impl LoseConditions {
+    pub fn lives_left(&self, stats: &RunStats) -> Option<u32> {
+        self.max_deaths
+            .map(|max_deaths| max_deaths.saturating_sub(stats.deaths))
+    }
+}
+
+fn reset_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
+
+fn apply_lose_conditions(settings: Res<Settings>, mut lose_conditions: ResMut<LoseConditions>) {
+    lose_conditions.clone_from(&settings.lose_conditions);
+}
+
+fn count_deaths(
+    _trigger: Trigger<OnDeath>,
+    mut stats: ResMut<RunStats>,
+    lose_conditions: Res<LoseConditions>,
+    mut commands: Commands,
+) {
+    stats.deaths += 1;
+    if lose_conditions.lives_left(&stats) == Some(0) {
+        commands.trigger(EndGameCondition::OutOfLives);
+    }
+}
+
+fn count_detections(
+    _trigger: Trigger<PlayerDetected>,
+    mut stats: ResMut<RunStats>,
+    lose_conditions: Res<LoseConditions>,
+    mut commands: Commands,
+) {
+    stats.detections += 1;
+    if lose_conditions.one_detection {
+        commands.trigger(EndGameCondition::Caught);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    /// Runs the end game observers under `conditions`, returning every way the game ended
+    fn ending_after<E: Event>(
+        conditions: LoseConditions,
+        event: impl Fn() -> E,
+        times: usize,
+    ) -> Vec<EndGameCondition> {
+        #[derive(Resource, Default)]
+        struct Endings(Vec<EndGameCondition>);
+
+        let mut app = App::new();
+        app.insert_resource(conditions);
+        app.init_resource::<RunStats>();
+        app.init_resource::<Endings>();
+        app.observe(count_deaths);
+        app.observe(count_detections);
+        app.observe(
+            |trigger: Trigger<EndGameCondition>, mut endings: ResMut<Endings>| {
+                endings.0.push(*trigger.event());
+            },
+        );
+        for _ in 0..times {
+            app.world_mut().trigger(event());
+        }
+        app.world_mut().flush();
+        std::mem::take(&mut app.world_mut().resource_mut::<Endings>().0)
+    }
+
+    #[test_case(None, 10, vec![] ; "unlimited lives never run out")]
+    #[test_case(Some(3), 2, vec![] ; "lives left over")]
+    #[test_case(Some(3), 3, vec![EndGameCondition::OutOfLives] ; "last life lost")]
+    fn dying_ends_the_game_only_with_limited_lives(
+        max_deaths: Option<u32>,
+        deaths: usize,
+        expected: Vec<EndGameCondition>,
+    ) {
+        let conditions = LoseConditions {
+            max_deaths,
+            ..default()
+        };
+        assert_eq!(
+            ending_after(conditions, || OnDeath(Vec2::ZERO), deaths),
+            expected
+        );
+    }
+
+    #[test_case(false, vec![] ; "off")]
+    #[test_case(true, vec![EndGameCondition::Caught] ; "on")]
+    fn getting_spotted_ends_the_game_only_with_one_strike(
+        one_detection: bool,
+        expected: Vec<EndGameCondition>,
+    ) {
+        let conditions = LoseConditions {
+            one_detection,
+            ..default()
+        };
+        assert_eq!(ending_after(conditions, || PlayerDetected, 1), expected);
+    }
+
+    #[test]
+    fn unlimited_lives() {
+        let conditions = LoseConditions {
+            time_out: true,
+            max_deaths: None,
+            one_detection: false,
+        };
+        let stats = RunStats {
+            deaths: 100,
+            detections: 0,
+        };
+
+        assert_eq!(conditions.lives_left(&stats), None);
+    }
+
+    #[test]
+    fn limited_lives_run_out() {
+        let conditions = LoseConditions {
+            time_out: true,
+            max_deaths: Some(3),
+            one_detection: false,
+        };
+        let mut stats = RunStats {
+            deaths: 2,
+            ..default()
+        };
+
+        assert_eq!(conditions.lives_left(&stats), Some(1));
+        stats.deaths = 5;
+        assert_eq!(conditions.lives_left(&stats), Some(0));
+    }
 }
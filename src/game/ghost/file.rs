@@ -0,0 +1,315 @@
+//! A compact binary file format for ghost runs, so they can be kept between sessions and shared with friends.
+//!
+//! Layout, all little endian:
+//! - magic `GHST`
+//! - format version, `u16`
+//! - tick rate the run was recorded at in Hz, `u16`
+//! - level id length in bytes, `u16`, followed by the level id as UTF-8
+//! - number of ticks, `u32`
+//! - per tick: coordinates as two `i16`s, offset as two `i16`s in 1/`OFFSET_SCALE` of a cell,
+//!   and one byte holding the animation state in the low bits and whether the player was alive in the high bit
+
+use std::fmt;
+
+use bevy::prelude::*;
+
+use super::GhostRecord;
+use crate::game::animation::PlayerAnimationState;
+
+const MAGIC: &[u8; 4] = b"GHST";
+pub const GHOST_FILE_VERSION: u16 = 1;
+
+/// Offsets stay within half a cell either way, so this leaves plenty of headroom
+const OFFSET_SCALE: f32 = 8192.0;
+const ALIVE_BIT: u8 = 0b1000_0000;
+const HEADER_BYTES: usize = 14;
+const BYTES_PER_TICK: usize = 9;
+
+/// Everything needed to replay a run through one level
+#[derive(Debug, Clone, PartialEq)]
+pub struct GhostRun {
+    /// The LDtk iid of the level the run was recorded in
+    pub level_id: String,
+    pub tick_rate_hz: u16,
+    pub(super) records: Vec<GhostRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GhostFileError {
+    NotAGhostFile,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidLevelId,
+    InvalidAnimationState(u8),
+    /// The level id is longer than its length prefix can say, in bytes
+    LevelIdTooLong(usize),
+    /// The run has more ticks than its tick count can say
+    TooManyTicks(usize),
+}
+
+impl fmt::Display for GhostFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAGhostFile => write!(f, "not a ghost file"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported ghost file version {}", version)
+            }
+            Self::Truncated => write!(f, "ghost file ends early"),
+            Self::InvalidLevelId => write!(f, "level id isn't valid UTF-8"),
+            Self::InvalidAnimationState(byte) => write!(f, "unknown animation state {}", byte),
+            Self::LevelIdTooLong(len) => write!(f, "level id is too long ({} bytes)", len),
+            Self::TooManyTicks(count) => write!(f, "run is too long ({} ticks)", count),
+        }
+    }
+}
+
+impl std::error::Error for GhostFileError {}
+
+impl GhostRun {
+    pub fn encode(&self) -> Result<Vec<u8>, GhostFileError> {
+        let level_id = self.level_id.as_bytes();
+        let level_id_len = u16::try_from(level_id.len())
+            .map_err(|_| GhostFileError::LevelIdTooLong(level_id.len()))?;
+        let tick_count = u32::try_from(self.records.len())
+            .map_err(|_| GhostFileError::TooManyTicks(self.records.len()))?;
+        let mut bytes =
+            Vec::with_capacity(HEADER_BYTES + level_id.len() + self.records.len() * BYTES_PER_TICK);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&GHOST_FILE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.tick_rate_hz.to_le_bytes());
+        bytes.extend_from_slice(&level_id_len.to_le_bytes());
+        bytes.extend_from_slice(level_id);
+        bytes.extend_from_slice(&tick_count.to_le_bytes());
+        for record in &self.records {
+            for value in [
+                record.coord.x.round() as i16,
+                record.coord.y.round() as i16,
+                (record.offset.x * OFFSET_SCALE).round() as i16,
+                (record.offset.y * OFFSET_SCALE).round() as i16,
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            let alive = if record.is_alive { ALIVE_BIT } else { 0 };
+            bytes.push(animation_state_to_byte(record.anim_state) | alive);
+        }
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, GhostFileError> {
+        let mut reader = Reader(bytes);
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(GhostFileError::NotAGhostFile);
+        }
+        let version = reader.u16()?;
+        if version != GHOST_FILE_VERSION {
+            return Err(GhostFileError::UnsupportedVersion(version));
+        }
+        let tick_rate_hz = reader.u16()?;
+        let level_id_len = reader.u16()? as usize;
+        let level_id = std::str::from_utf8(reader.take(level_id_len)?)
+            .map_err(|_| GhostFileError::InvalidLevelId)?
+            .to_string();
+
+        let tick_count = reader.u32()? as usize;
+        if reader.0.len() < tick_count * BYTES_PER_TICK {
+            return Err(GhostFileError::Truncated);
+        }
+        let mut records = Vec::with_capacity(tick_count);
+        for _ in 0..tick_count {
+            let coord = Vec2::new(reader.i16()? as f32, reader.i16()? as f32);
+            let offset = Vec2::new(reader.i16()? as f32, reader.i16()? as f32) / OFFSET_SCALE;
+            let flags = reader.u8()?;
+            records.push(GhostRecord {
+                coord,
+                offset,
+                anim_state: animation_state_from_byte(flags & !ALIVE_BIT)?,
+                is_alive: flags & ALIVE_BIT != 0,
+            });
+        }
+
+        Ok(Self {
+            level_id,
+            tick_rate_hz,
+            records,
+        })
+    }
+
+    /// The same run played back at a different tick rate, so it keeps to real time
+    pub fn resampled(&self, tick_rate_hz: u16) -> Self {
+        if tick_rate_hz == self.tick_rate_hz || self.tick_rate_hz == 0 || self.records.is_empty() {
+            return self.clone();
+        }
+        let ratio = self.tick_rate_hz as f32 / tick_rate_hz as f32;
+        let tick_count = (self.records.len() as f32 / ratio).round().max(1.0) as usize;
+        let records = (0..tick_count)
+            .map(|tick| {
+                let source = ((tick as f32 * ratio) as usize).min(self.records.len() - 1);
+                self.records[source].clone()
+            })
+            .collect();
+        Self {
+            level_id: self.level_id.clone(),
+            tick_rate_hz,
+            records,
+        }
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        if self.tick_rate_hz == 0 {
+            return 0.0;
+        }
+        self.records.len() as f32 / self.tick_rate_hz as f32
+    }
+}
+
+fn animation_state_to_byte(state: PlayerAnimationState) -> u8 {
+    match state {
+        PlayerAnimationState::Idling => 0,
+        PlayerAnimationState::Walking => 1,
+        PlayerAnimationState::Rolling => 2,
+        PlayerAnimationState::FrontIdling => 3,
+        PlayerAnimationState::FrontWalking => 4,
+        PlayerAnimationState::FrontRolling => 5,
+    }
+}
+
+fn animation_state_from_byte(byte: u8) -> Result<PlayerAnimationState, GhostFileError> {
+    Ok(match byte {
+        0 => PlayerAnimationState::Idling,
+        1 => PlayerAnimationState::Walking,
+        2 => PlayerAnimationState::Rolling,
+        3 => PlayerAnimationState::FrontIdling,
+        4 => PlayerAnimationState::FrontWalking,
+        5 => PlayerAnimationState::FrontRolling,
+        _ => return Err(GhostFileError::InvalidAnimationState(byte)),
+    })
+}
+
+/// Reads little endian values off the front of a byte slice
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], GhostFileError> {
+        if self.0.len() < len {
+            return Err(GhostFileError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, GhostFileError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, GhostFileError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Result<i16, GhostFileError> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, GhostFileError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(x: f32, offset_x: f32, anim_state: PlayerAnimationState) -> GhostRecord {
+        GhostRecord {
+            coord: Vec2::new(x, 3.0),
+            offset: Vec2::new(offset_x, -0.25),
+            anim_state,
+            is_alive: true,
+        }
+    }
+
+    fn run() -> GhostRun {
+        let mut last = record(2.0, 0.0, PlayerAnimationState::Idling);
+        last.is_alive = false;
+        GhostRun {
+            level_id: "a2d4-level".to_string(),
+            tick_rate_hz: 60,
+            records: vec![
+                record(1.0, 0.125, PlayerAnimationState::Walking),
+                record(1.0, 0.375, PlayerAnimationState::FrontRolling),
+                last,
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let run = run();
+
+        let decoded = GhostRun::decode(&run.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded, run);
+    }
+
+    #[test]
+    fn is_compact() {
+        let run = run();
+
+        assert_eq!(
+            run.encode().unwrap().len(),
+            HEADER_BYTES + run.level_id.len() + run.records.len() * BYTES_PER_TICK
+        );
+    }
+
+    #[test]
+    fn refuses_to_encode_level_ids_too_long_for_the_header() {
+        let mut run = run();
+        run.level_id = "a".repeat(u16::MAX as usize + 1);
+
+        assert_eq!(
+            run.encode(),
+            Err(GhostFileError::LevelIdTooLong(u16::MAX as usize + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(
+            GhostRun::decode(b"PNG\x89 not a ghost"),
+            Err(GhostFileError::NotAGhostFile)
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut bytes = run().encode().unwrap();
+        bytes[4..6].copy_from_slice(&(GHOST_FILE_VERSION + 1).to_le_bytes());
+
+        assert_eq!(
+            GhostRun::decode(&bytes),
+            Err(GhostFileError::UnsupportedVersion(GHOST_FILE_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = run().encode().unwrap();
+
+        assert_eq!(
+            GhostRun::decode(&bytes[..bytes.len() - 1]),
+            Err(GhostFileError::Truncated)
+        );
+    }
+
+    #[test]
+    fn resampling_keeps_the_duration() {
+        let run = run();
+
+        let resampled = run.resampled(120);
+
+        assert_eq!(resampled.tick_rate_hz, 120);
+        assert_eq!(resampled.records.len(), 6);
+        assert_eq!(resampled.duration_secs(), run.duration_secs());
+        assert_eq!(resampled.records[1], run.records[0]);
+    }
+}
@@ -1,13 +1,17 @@
 use std::collections::VecDeque;
+use std::path::PathBuf;
 
 use bevy::prelude::*;
+use bevy_ecs_ldtk::LevelIid;
 
+use crate::game::campaign::LevelStarted;
 use crate::game::line_of_sight::vision::VisionArchetype;
 use crate::game::line_of_sight::CanRevealFog;
 pub use crate::game::movement::RollState;
 use crate::{
     game::{animation::PlayerAnimation, assets::ImageAsset},
-    screen::Screen,
+    screen::PlayState,
+    AppSet,
 };
 
 use super::{
@@ -17,15 +21,24 @@ use super::{
     grid::GridPosition,
     line_of_sight::PlayerLineOfSightBundle,
     spawn::{
+        door::CanOpenDoors,
         health::{OnDeath, SpawnPointGridPosition},
         player::Player,
     },
 };
 
+pub mod file;
+pub mod personal_best;
+
+use file::GhostRun;
+use personal_best::PersonalBestGhost;
+
 ///Handles ghosts.
 ///
 /// Records ghost data (player movement intent) during FixedUpdate.
 /// Replays
+///
+/// Runs can be exported to a ghost file and imported again from the pause menu, see [`file`].
 pub fn plugin(app: &mut App) {
     app.insert_resource(Time::<Fixed>::from_hz(60.0));
     app.insert_resource(CurrentRecordQueue::new());
@@ -33,19 +46,36 @@ pub fn plugin(app: &mut App) {
         ghosts: VecDeque::new(),
         max_ghosts: 10,
     });
+    app.add_plugins(personal_best::plugin);
     app.add_systems(
         FixedUpdate,
         (record_intent, replay_ghost, animate_ghost, ghost_visibility)
-            .run_if(in_state(Screen::Playing)),
+            .run_if(in_state(PlayState::Running))
+            .in_set(AppSet::RecordInput),
     );
     app.observe(on_death_spawn_new_ghost);
     app.observe(on_death_reset_ghosts);
-    app.observe(clean_up);
+    app.observe(export_ghost);
+    app.observe(import_ghost);
+    app.observe(clean_up::<EndGameCondition>);
+    app.observe(clean_up::<LevelStarted>);
 }
 
 #[derive(Event)]
 struct PleaseResetGhostsNow;
 
+/// Where the pause menu exports ghosts to share, and imports shared ones from
+#[cfg(not(target_family = "wasm"))]
+pub const SHARED_GHOST_PATH: &str = "shared.ghost";
+
+/// Save the current attempt at this level to a ghost file
+#[derive(Event, Debug)]
+pub struct ExportGhost(pub PathBuf);
+
+/// Load a ghost file, and replay it in the current level if it was recorded there
+#[derive(Event, Debug)]
+pub struct ImportGhost(pub PathBuf);
+
 fn record_intent(
     mut ghost_records: ResMut<CurrentRecordQueue>,
     query: Query<(&GridPosition, &PlayerAnimation), With<Player>>,
@@ -64,19 +94,27 @@ fn record_intent(
 
 const DEAD_GHOST_FADE_SPEED: f32 = 0.03;
 fn ghost_visibility(
-    mut query: Query<(Entity, &mut Sprite, &GhostRecordQueue), With<Ghost>>,
+    mut query: Query<
+        (Entity, &mut Sprite, &GhostRecordQueue, Has<CanOpenDoors>),
+        (With<Ghost>, Without<PersonalBestGhost>),
+    >,
     mut commands: Commands,
 ) {
-    for (entity, mut sprite, ghost_record_queue) in query.iter_mut() {
+    for (entity, mut sprite, ghost_record_queue, can_open_doors) in query.iter_mut() {
         if ghost_record_queue.current_record > 0
             && ghost_record_queue.records[ghost_record_queue.current_record - 1].is_alive
         {
             sprite.color = sprite.color.with_alpha(GHOST_DEFAULT_ALPHA);
+            if !can_open_doors {
+                commands.entity(entity).insert(CanOpenDoors);
+            }
         } else if sprite.color.alpha() > 0.0 {
             sprite.color = sprite
                 .color
                 .with_alpha(sprite.color.alpha() - DEAD_GHOST_FADE_SPEED);
-            commands.entity(entity).remove::<CanRevealFog>();
+            commands
+                .entity(entity)
+                .remove::<(CanRevealFog, CanOpenDoors)>();
         }
     }
 }
@@ -99,8 +137,9 @@ impl CurrentRecordQueue {
     }
 }
 
+/// One of the player's past lives, replaying what they did
 #[derive(Component)]
-struct Ghost;
+pub struct Ghost;
 
 #[derive(Component)]
 pub struct GhostRecordQueue {
@@ -117,6 +156,7 @@ struct GhostRecord {
 }
 
 const GHOST_DEFAULT_ALPHA: f32 = 0.3;
+const GHOST_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
 
 fn on_death_spawn_new_ghost(
     _trigger: Trigger<OnDeath>,
@@ -130,9 +170,6 @@ fn on_death_spawn_new_ghost(
     let Ok(spawn_point) = spawn_points.get_single() else {
         return;
     };
-    let layout = TextureAtlasLayout::from_grid(UVec2::splat(16), 7, 6, None, None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
-    let player_animation = PlayerAnimation::default();
 
     // if you die rolling, your ghost rolls infinitely, so we reset the last frame to Idling
     if let Some(mut entry) = current_record_queue.0.records.pop() {
@@ -141,55 +178,178 @@ fn on_death_spawn_new_ghost(
         current_record_queue.0.records.push(entry);
     }
 
-    let new_ghost = commands
+    let new_ghost = spawn_loop_ghost(
+        &mut commands,
+        &images,
+        &mut texture_atlas_layouts,
+        spawn_point.0,
+        current_record_queue.0.records.clone(),
+    );
+
+    ghost_queue.ghosts.push_back(new_ghost);
+    if ghost_queue.ghosts.len() > ghost_queue.max_ghosts {
+        if let Some(old_ghost) = ghost_queue.ghosts.pop_front() {
+            commands.entity(old_ghost).despawn_recursive();
+        }
+    }
+
+    current_record_queue.0.records.clear();
+    commands.trigger(PleaseResetGhostsNow);
+}
+
+/// Spawns a ghost at `start` that replays the given records, only there to be watched
+fn spawn_ghost(
+    commands: &mut Commands,
+    images: &ImageAssets,
+    texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
+    start: Vec2,
+    records: Vec<GhostRecord>,
+    color: Color,
+) -> Entity {
+    let layout = TextureAtlasLayout::from_grid(UVec2::splat(16), 7, 6, None, None);
+    let texture_atlas_layout = texture_atlas_layouts.add(layout);
+    let player_animation = PlayerAnimation::default();
+
+    commands
         .spawn((
             Name::new("Ghost"),
             Ghost,
             SpriteBundle {
                 texture: images[&ImageAsset::Player].clone_weak(),
                 sprite: Sprite {
-                    color: Color::srgba(0.5, 0.5, 0.5, GHOST_DEFAULT_ALPHA),
+                    color: color.with_alpha(GHOST_DEFAULT_ALPHA),
                     ..default()
                 },
                 transform: Transform::from_xyz(0.0, 0.0, 2.0),
                 ..Default::default()
             },
             TextureAtlas {
-                layout: texture_atlas_layout.clone(),
+                layout: texture_atlas_layout,
                 index: player_animation.get_atlas_index(),
             },
-            GridPosition::new(spawn_point.0.x, spawn_point.0.y),
+            GridPosition::new(start.x, start.y),
             RollState::default(),
             GhostRecordQueue {
-                records: current_record_queue.0.records.clone(),
+                records,
                 current_record: 0,
             },
-            PlayerLineOfSightBundle::default().with_vision_archetype(VisionArchetype::Ghost),
             player_animation,
         ))
-        .id();
+        .id()
+}
 
-    ghost_queue.ghosts.push_back(new_ghost);
-    if ghost_queue.ghosts.len() > ghost_queue.max_ghosts {
-        if let Some(old_ghost) = ghost_queue.ghosts.pop_front() {
-            commands.entity(old_ghost).despawn_recursive();
+/// Spawns a ghost of one of the player's past lives, which lifts the fog and holds doors open like they did
+fn spawn_loop_ghost(
+    commands: &mut Commands,
+    images: &ImageAssets,
+    texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
+    start: Vec2,
+    records: Vec<GhostRecord>,
+) -> Entity {
+    let ghost = spawn_ghost(
+        commands,
+        images,
+        texture_atlas_layouts,
+        start,
+        records,
+        GHOST_COLOR,
+    );
+    commands.entity(ghost).insert((
+        CanOpenDoors,
+        PlayerLineOfSightBundle::default().with_vision_archetype(VisionArchetype::Ghost),
+    ));
+    ghost
+}
+
+pub fn tick_rate_hz(time: &Time<Fixed>) -> u16 {
+    (1.0 / time.timestep().as_secs_f64()).round() as u16
+}
+
+fn export_ghost(
+    trigger: Trigger<ExportGhost>,
+    current_record_queue: Res<CurrentRecordQueue>,
+    levels: Query<&LevelIid>,
+    time: Res<Time<Fixed>>,
+) {
+    let path = &trigger.event().0;
+    let Some(level) = levels.iter().next() else {
+        warn!("Can't export a ghost without a level");
+        return;
+    };
+    let run = GhostRun {
+        level_id: level.get().clone(),
+        tick_rate_hz: tick_rate_hz(&time),
+        records: current_record_queue.0.records.clone(),
+    };
+    let result = run
+        .encode()
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| std::fs::write(path, bytes).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => info!(
+            "Exported a {:.2}s ghost to {}",
+            run.duration_secs(),
+            path.display()
+        ),
+        Err(e) => warn!("Couldn't export ghost to {}: {}", path.display(), e),
+    }
+}
+
+fn import_ghost(
+    trigger: Trigger<ImportGhost>,
+    levels: Query<&LevelIid>,
+    spawn_points: Query<&SpawnPointGridPosition, With<Player>>,
+    time: Res<Time<Fixed>>,
+    images: Res<ImageAssets>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut commands: Commands,
+) {
+    let path = &trigger.event().0;
+    let run = match std::fs::read(path) {
+        Ok(bytes) => GhostRun::decode(&bytes).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let run = match run {
+        Ok(run) => run,
+        Err(e) => {
+            warn!("Couldn't import ghost from {}: {}", path.display(), e);
+            return;
         }
+    };
+    if !levels.iter().any(|level| *level.get() == run.level_id) {
+        warn!(
+            "Not importing ghost from {}, it was recorded in another level",
+            path.display()
+        );
+        return;
     }
+    let Ok(spawn_point) = spawn_points.get_single() else {
+        return;
+    };
 
-    current_record_queue.0.records.clear();
-    commands.trigger(PleaseResetGhostsNow);
+    let run = run.resampled(tick_rate_hz(&time));
+    info!("Imported a {:.2}s ghost", run.duration_secs());
+    spawn_loop_ghost(
+        &mut commands,
+        &images,
+        &mut texture_atlas_layouts,
+        spawn_point.0,
+        run.records,
+    );
 }
 
 fn on_death_reset_ghosts(
     _trigger: Trigger<PleaseResetGhostsNow>,
     spawn_points: Query<&SpawnPointGridPosition>,
-    mut alive_ghosts: Query<
-        (&mut GridPosition, &mut GhostRecordQueue),
-        (With<Ghost>, With<CanRevealFog>),
-    >,
-    mut dead_ghosts: Query<
-        (Entity, &mut GridPosition, &mut GhostRecordQueue),
-        (With<Ghost>, Without<CanRevealFog>),
+    mut ghosts: Query<
+        (
+            Entity,
+            &mut GridPosition,
+            &mut GhostRecordQueue,
+            Has<CanRevealFog>,
+            Has<PersonalBestGhost>,
+        ),
+        With<Ghost>,
     >,
     mut commands: Commands,
 ) {
@@ -197,17 +357,14 @@ fn on_death_reset_ghosts(
         return;
     };
 
-    for (mut pos, mut velocities) in &mut alive_ghosts {
+    for (ghost, mut pos, mut velocities, alive, personal_best) in &mut ghosts {
         velocities.current_record = 0;
         pos.coordinates.x = spawn_point.0.x;
         pos.coordinates.y = spawn_point.0.y;
-    }
-
-    for (ghost, mut pos, mut velocities) in &mut dead_ghosts {
-        velocities.current_record = 0;
-        pos.coordinates.x = spawn_point.0.x;
-        pos.coordinates.y = spawn_point.0.y;
-        commands.entity(ghost).insert(CanRevealFog);
+        // past lives that died come back to life, the personal best never could affect the level
+        if !alive && !personal_best {
+            commands.entity(ghost).insert((CanRevealFog, CanOpenDoors));
+        }
     }
 }
 
@@ -222,14 +379,19 @@ fn replay_ghost(mut query: Query<(&mut GhostRecordQueue, &mut GridPosition)>) {
     }
 }
 
-fn clean_up(
-    _trigger: Trigger<EndGameCondition>,
+/// Ghosts only make sense in the level they were recorded in
+fn clean_up<E: Event>(
+    _trigger: Trigger<E>,
     query: Query<Entity, With<Ghost>>,
+    mut ghost_queue: ResMut<GhostQueue>,
+    mut current_record_queue: ResMut<CurrentRecordQueue>,
     mut commands: Commands,
 ) {
     for entity in &query {
         commands.entity(entity).despawn_recursive();
     }
+    ghost_queue.ghosts.clear();
+    current_record_queue.0.records.clear();
 }
 
 fn animate_ghost(mut query: Query<(&GhostRecordQueue, &mut Sprite, &mut PlayerAnimation)>) {
@@ -0,0 +1,288 @@
+//! A racing ghost of the fastest time the player has reached the goal of each level.
+//! It replays alongside the player on later attempts, and the HUD shows how far ahead or behind of it they are.
+//! Native builds keep personal bests between sessions, as ghost files in [`PERSONAL_BEST_DIR`].
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{LevelEvent, LevelIid};
+
+use super::file::GhostRun;
+use super::{
+    record_intent, spawn_ghost, tick_rate_hz, CurrentRecordQueue, GhostRecord, GhostRecordQueue,
+};
+use crate::game::assets::ImageAssets;
+use crate::game::campaign::LevelCompleted;
+use crate::game::grid::GridPosition;
+use crate::game::spawn::player::Player;
+use crate::screen::PlayState;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<PersonalBests>();
+    app.init_resource::<PersonalBestDelta>();
+
+    // systems
+    app.add_systems(Update, spawn_personal_best_ghost);
+    app.add_systems(
+        FixedUpdate,
+        update_personal_best_delta
+            .after(record_intent)
+            .run_if(in_state(PlayState::Running)),
+    );
+    app.observe(record_personal_best);
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub const PERSONAL_BEST_DIR: &str = "ghosts";
+
+/// Gold, so it stands out from the ghosts of past lives
+const PERSONAL_BEST_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+
+/// How far either side of the current time to look for the point of the best run the player is at
+const DELTA_SEARCH_SECS: f32 = 5.0;
+
+/// The ghost racing the player through the level. It's only there to race against, so unlike the ghosts of past
+/// lives it doesn't lift the fog, hold doors open or fool enemies.
+#[derive(Component)]
+pub struct PersonalBestGhost;
+
+/// The fastest run to the goal of each level, by level id
+#[derive(Resource, Default)]
+pub struct PersonalBests {
+    runs: HashMap<String, GhostRun>,
+}
+
+impl PersonalBests {
+    /// The best run for a level, loading it from disk the first time it's asked for
+    pub fn get(&mut self, level_id: &str) -> Option<&GhostRun> {
+        if !self.runs.contains_key(level_id) {
+            if let Some(run) = load(level_id) {
+                self.runs.insert(level_id.to_string(), run);
+            }
+        }
+        self.runs.get(level_id)
+    }
+
+    /// Keeps the run if it's faster than the best so far, and says whether it was
+    pub fn submit(&mut self, run: GhostRun) -> bool {
+        if let Some(best) = self.get(&run.level_id) {
+            if best.duration_secs() <= run.duration_secs() {
+                return false;
+            }
+        }
+        save(&run);
+        self.runs.insert(run.level_id.clone(), run);
+        true
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn personal_best_path(level_id: &str) -> std::path::PathBuf {
+    std::path::Path::new(PERSONAL_BEST_DIR).join(format!("{}.ghost", level_id))
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn load(level_id: &str) -> Option<GhostRun> {
+    let path = personal_best_path(level_id);
+    let bytes = std::fs::read(&path).ok()?;
+    GhostRun::decode(&bytes)
+        .map_err(|e| warn!("Ignoring personal best in {}: {}", path.display(), e))
+        .ok()
+}
+
+#[cfg(target_family = "wasm")]
+fn load(_level_id: &str) -> Option<GhostRun> {
+    None
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn save(run: &GhostRun) {
+    let path = personal_best_path(&run.level_id);
+    let result = run.encode().map_err(|e| e.to_string()).and_then(|bytes| {
+        std::fs::create_dir_all(PERSONAL_BEST_DIR)
+            .and_then(|()| std::fs::write(&path, bytes))
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        warn!("Couldn't save personal best to {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn save(_run: &GhostRun) {}
+
+/// Seconds the player is behind the best run at `tick`, or ahead of it if negative.
+/// Matches the player's position to the closest point of the best run within a few seconds of the same time,
+/// so crossing the best run's path later on doesn't throw it off.
+fn time_delta(best: &[GhostRecord], tick: usize, position: Vec2, tick_rate_hz: u16) -> Option<f32> {
+    if best.is_empty() || tick_rate_hz == 0 {
+        return None;
+    }
+    let window = (DELTA_SEARCH_SECS * tick_rate_hz as f32) as usize;
+    let last = best.len() - 1;
+    let first = tick.saturating_sub(window).min(last);
+    let closest = (first..=(tick + window).min(last)).min_by(|a, b| {
+        let distance = |i: &usize| (best[*i].coord + best[*i].offset).distance_squared(position);
+        distance(a)
+            .total_cmp(&distance(b))
+            .then_with(|| a.abs_diff(tick).cmp(&b.abs_diff(tick)))
+    })?;
+    Some((tick as f32 - closest as f32) / tick_rate_hz as f32)
+}
+
+/// How far behind the personal best the player is right now, in seconds, if there is one for this level
+#[derive(Resource, Default, Debug)]
+pub struct PersonalBestDelta(pub Option<f32>);
+
+fn record_personal_best(
+    _trigger: Trigger<LevelCompleted>,
+    current_record_queue: Res<CurrentRecordQueue>,
+    levels: Query<&LevelIid>,
+    time: Res<Time<Fixed>>,
+    mut personal_bests: ResMut<PersonalBests>,
+) {
+    let Some(level) = levels.iter().next() else {
+        return;
+    };
+    if current_record_queue.0.records.is_empty() {
+        return;
+    }
+    let run = GhostRun {
+        level_id: level.get().clone(),
+        tick_rate_hz: tick_rate_hz(&time),
+        records: current_record_queue.0.records.clone(),
+    };
+    let duration = run.duration_secs();
+    if personal_bests.submit(run) {
+        info!("New personal best: {:.2}s", duration);
+    }
+}
+
+fn spawn_personal_best_ghost(
+    mut level_events: EventReader<LevelEvent>,
+    mut personal_bests: ResMut<PersonalBests>,
+    time: Res<Time<Fixed>>,
+    images: Res<ImageAssets>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut commands: Commands,
+) {
+    for level_event in level_events.read() {
+        let LevelEvent::Spawned(level_iid) = level_event else {
+            continue;
+        };
+        let Some(best) = personal_bests.get(level_iid.get()) else {
+            continue;
+        };
+        let best = best.resampled(tick_rate_hz(&time));
+        let Some(start) = best.records.first().map(|record| record.coord) else {
+            continue;
+        };
+        let ghost = spawn_ghost(
+            &mut commands,
+            &images,
+            &mut texture_atlas_layouts,
+            start,
+            best.records,
+            PERSONAL_BEST_COLOR,
+        );
+        commands
+            .entity(ghost)
+            .insert((Name::new("Personal Best Ghost"), PersonalBestGhost));
+    }
+}
+
+fn update_personal_best_delta(
+    current_record_queue: Res<CurrentRecordQueue>,
+    player: Query<&GridPosition, With<Player>>,
+    best_ghosts: Query<&GhostRecordQueue, With<PersonalBestGhost>>,
+    time: Res<Time<Fixed>>,
+    mut delta: ResMut<PersonalBestDelta>,
+) {
+    let delta_now = match (player.get_single(), best_ghosts.get_single()) {
+        (Ok(position), Ok(best)) => {
+            let (coord, offset) = position.get_values();
+            time_delta(
+                &best.records,
+                current_record_queue.0.records.len(),
+                coord + offset,
+                tick_rate_hz(&time),
+            )
+        }
+        _ => None,
+    };
+    delta.0 = delta_now;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::animation::PlayerAnimationState;
+
+    /// A best run walking right one cell a second, at 10 Hz
+    fn best_run() -> Vec<GhostRecord> {
+        (0..100)
+            .map(|tick| GhostRecord {
+                coord: Vec2::new(tick as f32 / 10.0, 0.0),
+                offset: Vec2::ZERO,
+                anim_state: PlayerAnimationState::Walking,
+                is_alive: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn level_with_the_best_run() {
+        assert_eq!(
+            time_delta(&best_run(), 30, Vec2::new(3.0, 0.0), 10),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn behind_the_best_run() {
+        assert_eq!(
+            time_delta(&best_run(), 30, Vec2::new(1.0, 0.0), 10),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn ahead_of_the_best_run() {
+        assert_eq!(
+            time_delta(&best_run(), 30, Vec2::new(4.5, 0.0), 10),
+            Some(-1.5)
+        );
+    }
+
+    #[test]
+    fn still_going_after_the_best_run_finished() {
+        assert_eq!(
+            time_delta(&best_run(), 120, Vec2::new(9.9, 0.0), 10),
+            Some(2.1)
+        );
+    }
+
+    #[test]
+    fn no_best_run() {
+        assert_eq!(time_delta(&[], 30, Vec2::ZERO, 10), None);
+    }
+
+    #[test]
+    fn slower_runs_dont_replace_the_best() {
+        let run = |ticks: usize| GhostRun {
+            level_id: "test-level-that-is-never-saved".to_string(),
+            tick_rate_hz: 10,
+            records: best_run()[..ticks].to_vec(),
+        };
+        let mut personal_bests = PersonalBests::default();
+        personal_bests.runs.insert(run(50).level_id, run(50));
+
+        assert!(!personal_bests.submit(run(60)));
+        assert_eq!(
+            personal_bests
+                .get(&run(50).level_id)
+                .map(GhostRun::duration_secs),
+            Some(5.0)
+        );
+    }
+}
@@ -6,7 +6,6 @@ use bevy::math::Vec2;
 use bevy::prelude::*;
 
 use crate::game::grid::GridPosition;
-use crate::geometry_2d::line_segment::LineSegment;
 
 #[derive(Resource, Debug, Reflect)]
 #[reflect(Resource)]
@@ -63,30 +62,10 @@ impl GridLayout {
         offset + Vec2::new(half_width_px, half_height_px)
     }
 
-    /// Get the positions of the corners of a position on the grid, in world (pixel) coordinates
-    pub fn corners(&self, grid_pos: &GridPosition) -> Corners {
-        let sw = self.grid_to_world(grid_pos);
-        let ne = sw
-            .with_x(sw.x + self.square_size)
-            .with_y(sw.y + self.square_size);
-        Corners {
-            southwest: sw,
-            northwest: Vec2::new(sw.x, ne.y),
-            northeast: ne,
-            southeast: Vec2::new(ne.x, sw.y),
-        }
-    }
-
-    /// Gets line segments for each side of the box that forms the grid position passed in
-    pub fn sides(&self, grid_pos: &GridPosition) -> Sides {
-        let corners = self.corners(grid_pos);
-
-        Sides {
-            north: LineSegment::new(corners.northwest, corners.northeast),
-            south: LineSegment::new(corners.southwest, corners.southeast),
-            east: LineSegment::new(corners.southeast, corners.northeast),
-            west: LineSegment::new(corners.southwest, corners.northwest),
-        }
+    /// The area covered by the whole grid, in world (pixel) coordinates
+    pub fn world_bounds(&self) -> Rect {
+        let size_px = Vec2::new(self.width as f32, self.height as f32) * self.square_size;
+        Rect::from_corners(self.origin, self.origin + size_px)
     }
 
     pub fn bounding_box(
@@ -148,28 +127,6 @@ impl GridBoundingBox {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Corners {
-    pub southwest: Vec2,
-    pub northwest: Vec2,
-    pub northeast: Vec2,
-    pub southeast: Vec2,
-}
-
-#[derive(Copy, Clone, Debug)]
-pub struct Sides {
-    pub north: LineSegment,
-    pub east: LineSegment,
-    pub south: LineSegment,
-    pub west: LineSegment,
-}
-
-impl Sides {
-    pub fn _all(&self) -> Vec<LineSegment> {
-        vec![self.north, self.east, self.south, self.west]
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use test_case::test_case;
@@ -212,4 +169,16 @@ mod tests {
 
         assert_eq!(25, neighbors.len(), "{:?}", neighbors);
     }
+
+    #[test]
+    fn world_bounds() {
+        let mut grid = GridLayout::new(64, 32);
+        grid.origin = Vec2::new(10., 20.);
+
+        let bounds = grid.world_bounds();
+
+        assert_eq!(bounds.min, Vec2::new(10., 20.));
+        assert_eq!(bounds.max, Vec2::new(10. + 64. * 16., 20. + 32. * 16.));
+        assert_eq!(bounds.center(), grid.center_worldpos());
+    }
 }
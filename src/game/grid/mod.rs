@@ -10,20 +10,30 @@ use crate::game::spawn::level::LevelWalls;
 use crate::AppSet;
 
 pub mod grid_layout;
+pub mod ray_cast;
 
 pub fn plugin(app: &mut App) {
     app.init_resource::<GridLayout>();
     app.add_systems(Update, update_grid_when_level_changes);
     // app.observe(fix_grid_position_system);
 
+    app.add_systems(FixedPreUpdate, restore_tick_translations);
     app.add_systems(
-        Update,
+        FixedUpdate,
         set_real_position_based_on_grid.in_set(AppSet::UpdateWorld),
     );
+    app.add_systems(
+        Update,
+        interpolate_between_ticks.in_set(AppSet::UpdateWorld),
+    );
+    app.observe(start_tracking_tick_translations);
 
     app.register_type::<(GridPosition, GridLayout)>();
 }
 
+/// Moving further than this in one tick is a teleport (respawning, rewinding...), which is drawn as a jump instead of smoothed
+const TELEPORT_DISTANCE: f32 = 32.;
+
 #[derive(Component)]
 pub struct GridSprite;
 
@@ -56,11 +66,6 @@ impl GridPosition {
         (other.coordinates + other.offset) - (self.coordinates + self.offset)
     }
 
-    pub fn with_offset(mut self, offset: Vec2) -> Self {
-        self.offset = offset;
-        self
-    }
-
     /// If the offset is more than a whole cell, then update the coordinates (and bring the offset back within 0..1)
     pub fn fix_offset_overflow(&mut self) {
         if self.offset.x >= 0.5 {
@@ -163,16 +168,84 @@ fn _fix_grid_position_system(
     }
 }
 
+/// Where a grid entity was put in the world on the last two fixed ticks.
+/// Frames land in between ticks, so the [`Transform`] is drawn somewhere between the two.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct TickTranslations {
+    pub previous: Vec2,
+    pub current: Vec2,
+}
+
+impl TickTranslations {
+    fn at(translation: Vec2) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+        }
+    }
+
+    fn advance(&mut self, translation: Vec2) {
+        self.previous = if self.current.distance(translation) > TELEPORT_DISTANCE {
+            translation
+        } else {
+            self.current
+        };
+        self.current = translation;
+    }
+
+    /// Where to draw it, `fraction` of the way from the previous tick to the current one
+    pub fn lerp(&self, fraction: f32) -> Vec2 {
+        self.previous.lerp(self.current, fraction)
+    }
+}
+
+fn start_tracking_tick_translations(
+    trigger: Trigger<OnAdd, GridPosition>,
+    positions: Query<&GridPosition>,
+    grid: Res<GridLayout>,
+    mut commands: Commands,
+) {
+    let Ok(position) = positions.get(trigger.entity()) else {
+        return;
+    };
+    commands
+        .entity(trigger.entity())
+        .insert(TickTranslations::at(grid.grid_to_world(position)));
+}
+
 /// Any entity that has a GridPosition and a Transform gets put in the world wherever its grid position says.
 /// This does mean that Transform mutations get overwritten by grid position calculated ones.
 pub fn set_real_position_based_on_grid(
-    mut query: Query<(&mut Transform, &GridPosition)>,
+    mut query: Query<(&mut Transform, &GridPosition, Option<&mut TickTranslations>)>,
     grid: Res<GridLayout>,
 ) {
-    for (mut t, gp) in query.iter_mut() {
+    for (mut t, gp, ticks) in query.iter_mut() {
         let pos = grid.grid_to_world(gp);
         t.translation.x = pos.x;
         t.translation.y = pos.y;
+        if let Some(mut ticks) = ticks {
+            ticks.advance(pos);
+        }
+    }
+}
+
+/// The simulation only ever sees where things are on the tick, never where they were last drawn
+fn restore_tick_translations(mut query: Query<(&mut Transform, &TickTranslations)>) {
+    for (mut t, ticks) in query.iter_mut() {
+        t.translation.x = ticks.current.x;
+        t.translation.y = ticks.current.y;
+    }
+}
+
+fn interpolate_between_ticks(
+    mut query: Query<(&mut Transform, &TickTranslations)>,
+    time: Res<Time<Fixed>>,
+) {
+    let fraction = time.overstep_fraction();
+    for (mut t, ticks) in query.iter_mut() {
+        let pos = ticks.lerp(fraction);
+        t.translation.x = pos.x;
+        t.translation.y = pos.y;
     }
 }
 
@@ -201,4 +274,14 @@ mod tests {
         assert_vec2_close!(bmina.coordinates, Vec2::new(1., 1.));
         assert_vec2_close!(bmina.offset, Vec2::new(0.4, 0.4));
     }
+
+    #[test]
+    fn tick_translations_smooth_steps_but_not_teleports() {
+        let mut ticks = TickTranslations::at(Vec2::ZERO);
+        ticks.advance(Vec2::new(2., 0.));
+        assert_eq!(ticks.lerp(0.5), Vec2::new(1., 0.));
+
+        ticks.advance(Vec2::new(200., 0.));
+        assert_eq!(ticks.lerp(0.5), Vec2::new(200., 0.));
+    }
 }
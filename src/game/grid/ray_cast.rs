@@ -0,0 +1,199 @@
+//! Finds the first solid square along a ray, stepping from square to square across the grid (a DDA traversal, after
+//! Amanatides & Woo's "A Fast Voxel Traversal Algorithm"), so only the squares the ray passes through are looked at.
+//!
+//! Everything is in grid units: squares are centred on whole numbers and reach half a square either side.
+
+use bevy::prelude::*;
+
+use crate::game::spawn::level::{LevelVisionBlockers, LevelWalls};
+
+/// Where a ray stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// The solid square it ran into
+    pub square: IVec2,
+    /// Where it met the edge of that square
+    pub point: Vec2,
+    /// Which way the side it hit faces, back towards the ray. Zero when the ray started inside the square.
+    pub normal: Vec2,
+    /// How far it got from its start
+    pub distance: f32,
+}
+
+/// A grid of solid and open squares that rays can be cast across
+pub trait RayCast {
+    fn blocks(&self, square: IVec2) -> bool;
+
+    /// The first solid square a ray from `origin` towards `direction` runs into, within `max_distance`.
+    /// Passing exactly through a corner counts as touching both squares beside it, so rays don't slip between two
+    /// walls that only meet at a corner.
+    fn ray_cast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<RayHit> {
+        let direction = direction.try_normalize()?;
+        if !origin.is_finite() || max_distance.is_nan() || max_distance < 0. {
+            return None;
+        }
+
+        let mut square = (origin + 0.5).floor().as_ivec2();
+        if self.blocks(square) {
+            return Some(RayHit {
+                square,
+                point: origin,
+                normal: Vec2::ZERO,
+                distance: 0.,
+            });
+        }
+
+        // an axis the ray runs along is never stepped across, so its sign doesn't matter
+        let step = direction.signum().as_ivec2();
+        // how far along the ray it takes to cross a whole square, and to reach the next edge, on each axis
+        let step_distance = direction.abs().recip();
+        let mut edge_distance = Vec2::new(
+            distance_to_edge(origin.x, square.x, direction.x),
+            distance_to_edge(origin.y, square.y, direction.y),
+        );
+
+        loop {
+            let (distance, normal) = if edge_distance.x < edge_distance.y {
+                square.x += step.x;
+                let distance = edge_distance.x;
+                edge_distance.x += step_distance.x;
+                (distance, Vec2::new(-step.x as f32, 0.))
+            } else {
+                square.y += step.y;
+                let distance = edge_distance.y;
+                edge_distance.y += step_distance.y;
+                (distance, Vec2::new(0., -step.y as f32))
+            };
+            if distance > max_distance || !distance.is_finite() {
+                return None;
+            }
+            if self.blocks(square) {
+                return Some(RayHit {
+                    square,
+                    point: origin + direction * distance,
+                    normal,
+                    distance,
+                });
+            }
+        }
+    }
+}
+
+/// How far along the ray, moving by `direction` per unit, it is from `position` to the edge of `square` it's heading for
+fn distance_to_edge(position: f32, square: i32, direction: f32) -> f32 {
+    if direction > 0. {
+        (square as f32 + 0.5 - position) / direction
+    } else if direction < 0. {
+        (position - (square as f32 - 0.5)) / -direction
+    } else {
+        f32::INFINITY
+    }
+}
+
+impl RayCast for LevelWalls {
+    fn blocks(&self, square: IVec2) -> bool {
+        self.collides(square.x, square.y)
+    }
+}
+
+impl RayCast for LevelVisionBlockers {
+    fn blocks(&self, square: IVec2) -> bool {
+        self.collides(square.x, square.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::GridCoords;
+    use test_case::test_case;
+
+    use super::*;
+
+    /// A 10x10 level with a wall at (5, 5)
+    fn walls() -> LevelWalls {
+        LevelWalls {
+            wall_locations: [GridCoords::new(5, 5)].into_iter().collect(),
+            level_width: 10,
+            level_height: 10,
+        }
+    }
+
+    #[test_case(Vec2::new(2., 5.), Vec2::X, IVec2::new(5, 5), Vec2::new(4.5, 5.), Vec2::NEG_X ; "from the west")]
+    #[test_case(Vec2::new(8., 5.), Vec2::NEG_X, IVec2::new(5, 5), Vec2::new(5.5, 5.), Vec2::X ; "from the east")]
+    #[test_case(Vec2::new(5., 1.), Vec2::Y, IVec2::new(5, 5), Vec2::new(5., 4.5), Vec2::NEG_Y ; "from the south")]
+    #[test_case(Vec2::new(5., 8.), Vec2::NEG_Y, IVec2::new(5, 5), Vec2::new(5., 5.5), Vec2::Y ; "from the north")]
+    #[test_case(Vec2::new(2., 2.), Vec2::ONE, IVec2::new(5, 5), Vec2::new(4.5, 4.5), Vec2::NEG_X ; "into a corner")]
+    #[test_case(Vec2::new(2., 2.), Vec2::NEG_Y, IVec2::new(2, -1), Vec2::new(2., -0.5), Vec2::Y ; "off the edge of the level")]
+    fn hits_the_first_wall(
+        origin: Vec2,
+        direction: Vec2,
+        square: IVec2,
+        point: Vec2,
+        normal: Vec2,
+    ) {
+        let hit = walls().ray_cast(origin, direction, 100.).unwrap();
+
+        assert_eq!(hit.square, square);
+        assert!(hit.point.distance(point) < 1e-5, "{:?}", hit);
+        assert_eq!(hit.normal, normal);
+        assert!((hit.distance - origin.distance(point)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stops_at_the_max_distance() {
+        assert_eq!(walls().ray_cast(Vec2::new(2., 5.), Vec2::X, 2.4), None);
+        assert!(walls().ray_cast(Vec2::new(2., 5.), Vec2::X, 2.5).is_some());
+    }
+
+    #[test]
+    fn starting_inside_a_wall_hits_it_straight_away() {
+        let hit = walls().ray_cast(Vec2::new(5.2, 4.8), Vec2::X, 10.).unwrap();
+
+        assert_eq!(hit.square, IVec2::new(5, 5));
+        assert_eq!(hit.distance, 0.);
+        assert_eq!(hit.normal, Vec2::ZERO);
+    }
+
+    #[test]
+    fn goes_nowhere_without_a_direction() {
+        assert_eq!(walls().ray_cast(Vec2::new(2., 5.), Vec2::ZERO, 10.), None);
+    }
+
+    #[test]
+    fn slips_past_walls_it_misses() {
+        let hit = walls()
+            .ray_cast(Vec2::new(2., 5.6), Vec2::new(1., 0.05), 100.)
+            .unwrap();
+
+        assert_eq!(hit.square, IVec2::new(10, 6));
+    }
+
+    #[test]
+    fn cant_slip_between_walls_meeting_at_a_corner() {
+        let walls = LevelWalls {
+            wall_locations: [GridCoords::new(5, 4), GridCoords::new(4, 5)]
+                .into_iter()
+                .collect(),
+            level_width: 10,
+            level_height: 10,
+        };
+
+        let hit = walls.ray_cast(Vec2::new(2., 2.), Vec2::ONE, 100.).unwrap();
+
+        assert!([IVec2::new(5, 4), IVec2::new(4, 5)].contains(&hit.square));
+        assert!(hit.point.distance(Vec2::new(4.5, 4.5)) < 1e-5);
+    }
+
+    #[test]
+    fn vision_blockers_block_rays_too() {
+        let blockers = LevelVisionBlockers {
+            vision_blocker_locations: [GridCoords::new(3, 1)].into_iter().collect(),
+            level_width: 10,
+            level_height: 10,
+        };
+
+        let hit = blockers.ray_cast(Vec2::new(1., 1.), Vec2::X, 100.).unwrap();
+
+        assert_eq!(hit.square, IVec2::new(3, 1));
+    }
+}
@@ -6,6 +6,7 @@ use bevy::render::render_resource::{AsBindGroup, ShaderRef};
 use bevy::render::texture::{ImageSampler, ImageSamplerDescriptor};
 use bevy::sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle};
 
+use crate::game::campaign::LevelStarted;
 use crate::game::grid::grid_layout::GridLayout;
 use crate::game::grid::GridPosition;
 use crate::game::line_of_sight::vision::VisibleSquares;
@@ -26,32 +27,141 @@ pub(super) fn plugin(app: &mut App) {
             .chain()
             .in_set(AppSet::UpdateFog),
     );
+    app.observe(on_level_start_reset_fog);
 
     // app.register_type::<FogOfWarMaterial>();
 }
 
+/// How much of the level layout shows through the fog once it's been explored
+const EXPLORED_FOG_OPACITY: f32 = 0.6;
+
+/// How fast fog rolls back in once a square is out of sight, per second
+const RECOVERY_SPEED: f32 = 2.0;
+
 #[derive(Component)]
 struct FogOfWar {
     width: u32,
     height: u32,
+    /// How thick the fog is on each square right now, from 0 where it's in sight to 1 where it isn't
     data: Vec<f32>,
+    /// Whether each square has ever been in sight this level
+    explored: Vec<bool>,
+    /// Covers every texel changed since the texture was last updated, in texture columns and rows
+    dirty: Option<URect>,
+    /// Covers every texel the fog is still rolling back in over, so the rest of the level can be left alone
+    recovering: Option<URect>,
 }
 
 impl FogOfWar {
+    fn new(width: u32, height: u32) -> Self {
+        let num_grid_squares = (width * height) as usize;
+        Self {
+            width,
+            height,
+            data: vec![1.0; num_grid_squares],
+            explored: vec![false; num_grid_squares],
+            // the texture starts out the same
+            dirty: None,
+            recovering: None,
+        }
+    }
+
     pub fn index(&self, x: u32, y: u32) -> u32 {
         let row = self.height - y - 1;
 
         self.width * row + x
     }
+
+    /// Where a grid square is in the texture, which counts rows down from the top
+    fn texel_position(&self, x: u32, y: u32) -> UVec2 {
+        UVec2::new(x, self.height - y - 1)
+    }
+
+    fn texel_index(&self, texel: UVec2) -> usize {
+        (self.width * texel.y + texel.x) as usize
+    }
+
+    /// Clears the fog from a square, and remembers it's been explored
+    fn reveal(&mut self, x: u32, y: u32) {
+        let index = self.index(x, y) as usize;
+        self.data[index] = 0.0;
+        self.explored[index] = true;
+
+        let texel = URect::from_corners(self.texel_position(x, y), self.texel_position(x, y));
+        self.dirty = Some(union(self.dirty, texel));
+        self.recovering = Some(union(self.recovering, texel));
+    }
+
+    /// Lets the fog roll back in over squares out of sight, without forgetting they've been explored
+    fn recover(&mut self, amount: f32) {
+        let Some(area) = self.recovering.take() else {
+            return;
+        };
+        for row in area.min.y..=area.max.y {
+            for column in area.min.x..=area.max.x {
+                let texel = UVec2::new(column, row);
+                let index = self.texel_index(texel);
+                let d = &mut self.data[index];
+                if *d < 1.0 {
+                    *d = (*d + amount).min(1.0);
+                    if *d < 1.0 {
+                        // shrinks to fit what's left, so squares stop being looked at once they're fogged over
+                        let still_recovering = URect::from_corners(texel, texel);
+                        self.recovering = Some(union(self.recovering, still_recovering));
+                    }
+                }
+            }
+        }
+        self.dirty = Some(union(self.dirty, area));
+    }
+
+    /// Forgets everything, for a new level
+    fn reset(&mut self) {
+        self.data.fill(1.0);
+        self.explored.fill(false);
+        self.recovering = None;
+        self.dirty = Some(URect::new(0, 0, self.width - 1, self.height - 1));
+    }
+
+    /// Copies the texels changed since last time into the texture data, fog then explored for each
+    fn write_dirty_texels(&mut self, texture_data: &mut [u8]) {
+        let Some(area) = self.dirty.take() else {
+            return;
+        };
+        for row in area.min.y..=area.max.y {
+            for column in area.min.x..=area.max.x {
+                let index = self.texel_index(UVec2::new(column, row));
+                texture_data[2 * index..2 * index + 2].copy_from_slice(&self.texel(index));
+            }
+        }
+    }
+
+    /// The two channels of a square's texel: how thick the fog is, and whether it's been explored
+    fn texel(&self, index: usize) -> [u8; 2] {
+        [
+            (self.data[index] * 255.0) as u8,
+            if self.explored[index] { 255 } else { 0 },
+        ]
+    }
+}
+
+/// The smallest rectangle covering both, where the corners of each are included
+fn union(rect: Option<URect>, other: URect) -> URect {
+    rect.map_or(other, |rect| rect.union(other))
 }
 
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
 struct FogOfWarMaterial {
+    /// The fog over squares that haven't been explored
     #[uniform(0)]
     color: LinearRgba,
+    /// Fog channel in red, explored channel in green
     #[texture(1)]
     #[sampler(2)]
     fog_texture: Handle<Image>,
+    /// The fog over squares that have been explored but are out of sight
+    #[uniform(3)]
+    explored_color: LinearRgba,
 }
 
 impl Material2d for FogOfWarMaterial {
@@ -110,8 +220,8 @@ fn setup_fog_of_war(
             depth_or_array_layers: 1,
         },
         bevy::render::render_resource::TextureDimension::D2,
-        &[255],
-        bevy::render::render_resource::TextureFormat::R8Unorm,
+        &[255, 0],
+        bevy::render::render_resource::TextureFormat::Rg8Unorm,
         RenderAssetUsages::all(),
     );
     fog_texture.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor::nearest());
@@ -125,6 +235,7 @@ fn setup_fog_of_war(
     let material = materials.add(FogOfWarMaterial {
         color: color.to_linear(),
         fog_texture: fog_texture_handle.clone(),
+        explored_color: color.with_alpha(EXPLORED_FOG_OPACITY).to_linear(),
     });
 
     let mesh_transform_grid_center = grid.center_worldpos();
@@ -142,11 +253,7 @@ fn setup_fog_of_war(
             .with_translation(mesh_transform_grid_center.extend(10.)),
             ..default()
         },
-        FogOfWar {
-            width,
-            height,
-            data: vec![1.0; num_grid_squares as usize],
-        },
+        FogOfWar::new(width, height),
     ));
 
     info!(
@@ -158,14 +265,16 @@ fn setup_fog_of_war(
 fn copy_data_to_texture(
     mut fog_query: Query<(&mut FogOfWar, &Handle<FogOfWarMaterial>)>,
     mut images: ResMut<Assets<Image>>,
-    mut fog_materials: ResMut<Assets<FogOfWarMaterial>>,
+    fog_materials: Res<Assets<FogOfWarMaterial>>,
 ) {
-    for (fog, material_handle) in fog_query.iter_mut() {
-        if let Some(material) = fog_materials.get_mut(material_handle) {
+    for (mut fog, material_handle) in fog_query.iter_mut() {
+        // getting the image mutably sends it to the GPU again, so leave it be while nothing has changed
+        if fog.dirty.is_none() {
+            continue;
+        }
+        if let Some(material) = fog_materials.get(material_handle) {
             if let Some(texture) = images.get_mut(&material.fog_texture) {
-                for (i, value) in fog.data.iter().enumerate() {
-                    texture.data[i] = (*value * 255.0) as u8;
-                }
+                fog.write_dirty_texels(&mut texture.data);
             }
         }
     }
@@ -194,19 +303,159 @@ fn reveal_fog_of_war(
         }
 
         for square in with_neighbors.iter() {
-            let index = fog.index(square.x as u32, square.y as u32);
-            fog.data[index as usize] = 0.0;
-            // (fog.data[index as usize] - REVEAL_SPEED * time.delta_seconds()).max(0.0);
+            fog.reveal(square.x as u32, square.y as u32);
         }
     }
 }
 
+/// A new level starts out completely unexplored
+fn on_level_start_reset_fog(
+    _trigger: Trigger<LevelStarted>,
+    mut fog_of_war_query: Query<&mut FogOfWar>,
+) {
+    for mut fog in fog_of_war_query.iter_mut() {
+        fog.reset();
+    }
+}
+
 fn recover_fog_of_war(mut fog_of_war_query: Query<&mut FogOfWar>, time: Res<Time>) {
-    const RECOVERY_SPEED: f32 = 2.0;
-    for mut s in fog_of_war_query.iter_mut() {
-        let data = &mut s.data;
-        for d in data.iter_mut() {
-            *d = (*d + RECOVERY_SPEED * time.delta_seconds()).min(1.0);
+    for mut fog in fog_of_war_query.iter_mut() {
+        fog.recover(RECOVERY_SPEED * time.delta_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    /// The texture data, as it's uploaded
+    fn texture_data(fog: &mut FogOfWar) -> Vec<u8> {
+        let mut texture_data = [255, 0].repeat((fog.width * fog.height) as usize);
+        fog.dirty = Some(URect::new(0, 0, fog.width - 1, fog.height - 1));
+        fog.write_dirty_texels(&mut texture_data);
+        texture_data
+    }
+
+    #[test]
+    fn remembers_explored_squares_once_the_fog_is_back() {
+        let mut fog = FogOfWar::new(4, 3);
+
+        fog.reveal(1, 2);
+        let index = fog.index(1, 2) as usize;
+        assert_eq!(fog.texel(index), [0, 255]);
+
+        fog.recover(0.5);
+        assert_eq!(fog.texel(index), [127, 255]);
+        fog.recover(1.0);
+        assert_eq!(fog.texel(index), [255, 255]);
+        assert_eq!(fog.texel(fog.index(2, 2) as usize), [255, 0]);
+    }
+
+    #[test]
+    fn a_new_level_is_unexplored() {
+        let mut fog = FogOfWar::new(4, 3);
+        fog.reveal(3, 0);
+
+        fog.reset();
+
+        assert!(fog.explored.iter().all(|explored| !explored));
+        assert!(fog.data.iter().all(|d| *d == 1.0));
+    }
+
+    #[test]
+    fn only_changed_texels_are_dirty() {
+        let mut fog = FogOfWar::new(8, 8);
+        fog.reveal(1, 6);
+        fog.reveal(3, 5);
+
+        assert_eq!(fog.dirty, Some(URect::new(1, 1, 3, 2)));
+        fog.write_dirty_texels(&mut [0; 128]);
+        assert_eq!(fog.dirty, None);
+    }
+
+    #[test]
+    fn stops_recovering_once_fogged_over() {
+        let mut fog = FogOfWar::new(8, 8);
+        fog.reveal(1, 6);
+        fog.recover(0.5);
+        fog.reveal(3, 5);
+
+        fog.recover(0.5);
+        assert_eq!(fog.recovering, Some(URect::new(3, 2, 3, 2)));
+        fog.recover(0.5);
+        assert_eq!(fog.recovering, None);
+        fog.recover(0.5);
+        assert_eq!(fog.dirty, Some(URect::new(1, 1, 3, 2)));
+    }
+
+    #[test]
+    fn uploads_the_same_as_copying_everything() {
+        let mut fog = FogOfWar::new(16, 8);
+        let mut uploaded = texture_data(&mut fog);
+        for step in 0..20 {
+            fog.recover(0.3);
+            fog.reveal(step % 16, step % 8);
+            fog.reveal((step * 7) % 16, 0);
+            fog.write_dirty_texels(&mut uploaded);
+
+            assert_eq!(uploaded, texture_data(&mut fog), "step {}", step);
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture fog_of_war` to see how long a frame of fog takes on a big map
+    #[test]
+    #[ignore]
+    fn benchmark_large_map() {
+        const SIZE: u32 = 256;
+        const FRAMES: u32 = 200;
+        const SIGHT_RADIUS: i32 = 30;
+
+        for (name, revealing) in [("someone walking", true), ("nobody in sight", false)] {
+            let mut fog = FogOfWar::new(SIZE, SIZE);
+            let mut texture_data = [255, 0].repeat((SIZE * SIZE) as usize);
+            let mut took = [0., 0.];
+
+            for frame in 0..FRAMES {
+                if revealing {
+                    let center = IVec2::new(40 + frame as i32 % 150, 128);
+                    for x in -SIGHT_RADIUS..=SIGHT_RADIUS {
+                        for y in -SIGHT_RADIUS..=SIGHT_RADIUS {
+                            if x * x + y * y <= SIGHT_RADIUS * SIGHT_RADIUS {
+                                fog.reveal((center.x + x) as u32, (center.y + y) as u32);
+                            }
+                        }
+                    }
+                }
+
+                // how it was done before keeping track of what changed
+                let mut everything = FogOfWar::new(SIZE, SIZE);
+                everything.data.clone_from(&fog.data);
+                everything.explored.clone_from(&fog.explored);
+                let start = Instant::now();
+                for d in everything.data.iter_mut() {
+                    *d = (*d + 0.03).min(1.0);
+                }
+                for (i, texel) in texture_data.chunks_exact_mut(2).enumerate() {
+                    texel.copy_from_slice(&everything.texel(i));
+                }
+                took[0] += start.elapsed().as_secs_f64();
+
+                let start = Instant::now();
+                fog.recover(0.03);
+                fog.write_dirty_texels(&mut texture_data);
+                took[1] += start.elapsed().as_secs_f64();
+            }
+
+            println!(
+                "{}x{} fog, {}: whole map {:.1}µs, dirty rectangles {:.1}µs per frame",
+                SIZE,
+                SIZE,
+                name,
+                took[0] * 1e6 / FRAMES as f64,
+                took[1] * 1e6 / FRAMES as f64
+            );
         }
     }
 }
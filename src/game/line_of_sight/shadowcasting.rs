@@ -0,0 +1,578 @@
+//! Recursive symmetric shadowcasting over the level's vision blockers, after Albert Ford's
+//! [Symmetric Shadowcasting](https://www.albertford.com/shadowcasting/).
+//!
+//! The view around a viewer is split into four quarters, and each is scanned row by row moving away from them,
+//! narrowing as walls cast their shadows. Only squares that could still be seen are ever looked at, instead of every
+//! square in range being checked against every wall. A floor square is seen when the line from the middle of the
+//! viewer's square to the middle of it is clear, so vision is symmetric: whoever the player can see can see them back.
+//! Walls are seen when any part of them is.
+
+use std::f32::consts::{FRAC_PI_4, PI};
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::game::spawn::level::LevelVisionBlockers;
+
+/// The squares visible from `origin`, looking towards `facing`. Squares further than `range` away, or more than
+/// `field_of_view_radians` either side of `facing`, are out of sight. Of the squares right next to the viewer, only
+/// the one straight ahead is seen, so nothing sneaks up on them from behind.
+pub fn visible_squares(
+    blockers: &LevelVisionBlockers,
+    origin: IVec2,
+    facing: Vec2,
+    range: f32,
+    field_of_view_radians: f32,
+) -> HashSet<IVec2> {
+    let mut visible = HashSet::new();
+    for quadrant in Quadrant::ALL {
+        let caster = Shadowcaster {
+            blockers,
+            origin,
+            facing,
+            range,
+            field_of_view_radians,
+            quadrant,
+        };
+        if !caster.quadrant_in_view() {
+            continue;
+        }
+        caster.reveal(origin, &mut visible);
+        caster.scan(
+            Row {
+                depth: 1,
+                start: Slope::new(-1, 1),
+                end: Slope::new(1, 1),
+            },
+            &mut visible,
+        );
+    }
+    visible
+}
+
+/// One of the four quarters around the viewer, centred on a compass direction
+#[derive(Debug, Clone, Copy)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    const ALL: [Self; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    fn direction(self) -> Vec2 {
+        match self {
+            Self::North => Vec2::Y,
+            Self::East => Vec2::X,
+            Self::South => Vec2::NEG_Y,
+            Self::West => Vec2::NEG_X,
+        }
+    }
+
+    /// The square `depth` rows away from `origin` in this quadrant, `column` squares to the side
+    fn square(self, origin: IVec2, depth: i32, column: i32) -> IVec2 {
+        origin
+            + match self {
+                Self::North => IVec2::new(column, depth),
+                Self::East => IVec2::new(depth, column),
+                Self::South => IVec2::new(column, -depth),
+                Self::West => IVec2::new(-depth, column),
+            }
+    }
+}
+
+/// A slope as an exact fraction, so rows never round the wrong way
+#[derive(Debug, Clone, Copy)]
+struct Slope {
+    rise: i32,
+    run: i32,
+}
+
+impl Slope {
+    fn new(rise: i32, run: i32) -> Self {
+        Self { rise, run }
+    }
+
+    /// The slope to the near-left corner of a square, where the shadow of a wall starts or ends
+    fn of_square(depth: i32, column: i32) -> Self {
+        Self::new(2 * column - 1, 2 * depth)
+    }
+
+    /// The column at this slope `depth` rows away, rounding halves up
+    fn column_rounding_up(self, depth: i32) -> i32 {
+        (2 * depth * self.rise + self.run).div_euclid(2 * self.run)
+    }
+
+    /// The column at this slope `depth` rows away, rounding halves down
+    fn column_rounding_down(self, depth: i32) -> i32 {
+        -(self.run - 2 * depth * self.rise).div_euclid(2 * self.run)
+    }
+}
+
+/// A row of a quadrant, between the shadows cast on it so far
+#[derive(Debug, Clone, Copy)]
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    fn columns(&self) -> RangeInclusive<i32> {
+        self.start.column_rounding_up(self.depth)..=self.end.column_rounding_down(self.depth)
+    }
+
+    fn next(&self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+
+    /// Whether the middle of the square in `column` is inside the row, rather than just part of it
+    fn is_symmetric(&self, column: i32) -> bool {
+        column * self.start.run >= self.depth * self.start.rise
+            && column * self.end.run <= self.depth * self.end.rise
+    }
+}
+
+struct Shadowcaster<'a> {
+    blockers: &'a LevelVisionBlockers,
+    origin: IVec2,
+    facing: Vec2,
+    range: f32,
+    field_of_view_radians: f32,
+    quadrant: Quadrant,
+}
+
+impl Shadowcaster<'_> {
+    /// Quadrants entirely outside the cone can be skipped
+    fn quadrant_in_view(&self) -> bool {
+        let angle = self.quadrant.direction().angle_between(self.facing).abs();
+        self.field_of_view_radians >= PI
+            || angle.is_nan()
+            || angle <= self.field_of_view_radians + FRAC_PI_4
+    }
+
+    fn scan(&self, mut row: Row, visible: &mut HashSet<IVec2>) {
+        if row.depth as f32 > self.range {
+            return;
+        }
+        let mut previous_was_wall = None;
+        for column in row.columns() {
+            let square = self.quadrant.square(self.origin, row.depth, column);
+            let is_wall = self.blockers.collides(square.x, square.y);
+            if is_wall || row.is_symmetric(column) {
+                self.reveal(square, visible);
+            }
+            match (previous_was_wall, is_wall) {
+                // out of a wall's shadow
+                (Some(true), false) => row.start = Slope::of_square(row.depth, column),
+                // into one, so the rest of the rows before it carry on without this part
+                (Some(false), true) => self.scan(
+                    Row {
+                        end: Slope::of_square(row.depth, column),
+                        ..row.next()
+                    },
+                    visible,
+                ),
+                _ => {}
+            }
+            previous_was_wall = Some(is_wall);
+        }
+        if previous_was_wall == Some(false) {
+            self.scan(row.next(), visible);
+        }
+    }
+
+    /// Adds the square to the visible ones, if it's in the level, in range and inside the cone
+    fn reveal(&self, square: IVec2, visible: &mut HashSet<IVec2>) {
+        if square.x < 0
+            || square.y < 0
+            || square.x >= self.blockers.level_width
+            || square.y >= self.blockers.level_height
+        {
+            return;
+        }
+        let offset = (square - self.origin).as_vec2();
+        let distance = offset.length();
+        if distance <= 1.0 {
+            if self.origin.as_vec2() + self.facing == square.as_vec2() {
+                visible.insert(square);
+            }
+            return;
+        }
+        if distance > self.range {
+            return;
+        }
+        if offset.angle_between(self.facing).abs() > self.field_of_view_radians {
+            return;
+        }
+        visible.insert(square);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet as StdHashSet;
+    use std::time::Instant;
+
+    use bevy_ecs_ldtk::GridCoords;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::game::grid::grid_layout::GridLayout;
+    use crate::game::grid::GridPosition;
+    use crate::geometry_2d::line_segment::LineSegment;
+
+    use super::*;
+
+    const ALL_AROUND: f32 = 2. * PI;
+
+    /// A level from rows of `#` walls and `.` floor, top row first, with the viewer at `@`
+    fn level(rows: &[&str]) -> (LevelVisionBlockers, IVec2) {
+        let height = rows.len() as i32;
+        let mut blockers = LevelVisionBlockers {
+            vision_blocker_locations: StdHashSet::new(),
+            level_width: rows[0].len() as i32,
+            level_height: height,
+        };
+        let mut viewer = IVec2::ZERO;
+        for (row, line) in rows.iter().enumerate() {
+            for (x, square) in line.chars().enumerate() {
+                let position = IVec2::new(x as i32, height - row as i32 - 1);
+                match square {
+                    '#' => {
+                        blockers
+                            .vision_blocker_locations
+                            .insert(GridCoords::new(position.x, position.y));
+                    }
+                    '@' => viewer = position,
+                    _ => {}
+                }
+            }
+        }
+        (blockers, viewer)
+    }
+
+    /// A level with walls scattered across it, walled in all round
+    fn random_level(rng: &mut ChaCha8Rng, size: i32, wall_chance: f64) -> LevelVisionBlockers {
+        let mut blockers = LevelVisionBlockers {
+            vision_blocker_locations: StdHashSet::new(),
+            level_width: size,
+            level_height: size,
+        };
+        for x in 0..size {
+            for y in 0..size {
+                let edge = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                if edge || rng.gen_bool(wall_chance) {
+                    blockers
+                        .vision_blocker_locations
+                        .insert(GridCoords::new(x, y));
+                }
+            }
+        }
+        blockers
+    }
+
+    fn floor_squares(blockers: &LevelVisionBlockers) -> Vec<IVec2> {
+        (0..blockers.level_width)
+            .flat_map(|x| (0..blockers.level_height).map(move |y| IVec2::new(x, y)))
+            .filter(|square| !blockers.collides(square.x, square.y))
+            .collect()
+    }
+
+    /// How vision worked before shadowcasting: a ray from the viewer to every square in range, checked against the
+    /// sides of walls facing the viewer
+    fn ray_cast_visible_squares(
+        blockers: &LevelVisionBlockers,
+        origin: IVec2,
+        facing: Vec2,
+        range: f32,
+        field_of_view_radians: f32,
+    ) -> HashSet<IVec2> {
+        let grid = GridLayout::new(
+            blockers.level_width as usize,
+            blockers.level_height as usize,
+        );
+        let start = origin.as_vec2();
+
+        let mut edges = vec![];
+        for wall in &blockers.vision_blocker_locations {
+            let southwest = Vec2::new(wall.x as f32 - 0.5, wall.y as f32 - 0.5);
+            let northeast = southwest + Vec2::ONE;
+            let northwest = Vec2::new(southwest.x, northeast.y);
+            let southeast = Vec2::new(northeast.x, southwest.y);
+            if (start - southwest).length() > range {
+                continue;
+            }
+            if start.x > southwest.x && !blockers.collides(wall.x + 1, wall.y) {
+                edges.push(LineSegment::new(southeast, northeast))
+            } else if start.x < southwest.x && !blockers.collides(wall.x - 1, wall.y) {
+                edges.push(LineSegment::new(southwest, northwest))
+            }
+            if start.y > southwest.y && !blockers.collides(wall.x, wall.y + 1) {
+                edges.push(LineSegment::new(northwest, northeast))
+            } else if start.y < southwest.y && !blockers.collides(wall.x, wall.y - 1) {
+                edges.push(LineSegment::new(southwest, southeast))
+            }
+        }
+
+        let mut visible = HashSet::new();
+        for end in grid
+            .bounding_box(&GridPosition::from_ivec(&origin), range)
+            .coords_range()
+        {
+            let offset = end - start;
+            if offset.length() <= 1.0 {
+                if start + facing == end {
+                    visible.insert(end.as_ivec2());
+                }
+                continue;
+            }
+            if offset.length() > range {
+                continue;
+            }
+            let ray = LineSegment::new(start, end);
+            if ray.segment2d.direction.angle_between(facing).abs() > field_of_view_radians {
+                continue;
+            }
+            if edges.iter().any(|edge| ray.do_intersect(edge)) {
+                continue;
+            }
+            visible.insert(end.as_ivec2());
+        }
+        visible
+    }
+
+    #[test]
+    fn sees_the_whole_open_room_in_range() {
+        let (blockers, viewer) = level(&[
+            "#########", //
+            "#.......#",
+            "#.......#",
+            "#...@...#",
+            "#.......#",
+            "#.......#",
+            "#########",
+        ]);
+
+        let visible = visible_squares(&blockers, viewer, Vec2::X, 30., ALL_AROUND);
+
+        for square in floor_squares(&blockers) {
+            let next_to_viewer = (square - viewer).as_vec2().length() <= 1.0;
+            assert_eq!(
+                visible.contains(&square),
+                !next_to_viewer || square == viewer + IVec2::X,
+                "{:?}",
+                square
+            );
+        }
+        // and the walls around it
+        assert!(visible.contains(&IVec2::new(0, 0)));
+        assert!(visible.contains(&IVec2::new(8, 6)));
+    }
+
+    #[test]
+    fn walls_cast_shadows() {
+        let (blockers, viewer) = level(&[
+            "#########", //
+            "#.......#",
+            "#.@.#...#",
+            "#.......#",
+            "#########",
+        ]);
+
+        let visible = visible_squares(&blockers, viewer, Vec2::X, 30., ALL_AROUND);
+
+        assert!(visible.contains(&IVec2::new(4, 2)), "the wall itself");
+        assert!(!visible.contains(&IVec2::new(5, 2)));
+        assert!(!visible.contains(&IVec2::new(7, 2)));
+        assert!(!visible.contains(&IVec2::new(7, 3)));
+        assert!(visible.contains(&IVec2::new(5, 3)));
+        assert!(visible.contains(&IVec2::new(5, 1)));
+    }
+
+    #[test]
+    fn only_sees_inside_the_cone() {
+        let (blockers, viewer) = level(&[
+            "###########", //
+            "#.........#",
+            "#.........#",
+            "#....@....#",
+            "#.........#",
+            "#.........#",
+            "###########",
+        ]);
+
+        let visible = visible_squares(&blockers, viewer, Vec2::X, 30., FRAC_PI_4);
+
+        assert!(visible.contains(&IVec2::new(9, 3)));
+        assert!(visible.contains(&IVec2::new(9, 5)));
+        assert!(!visible.contains(&IVec2::new(6, 5)));
+        assert!(!visible.contains(&IVec2::new(3, 3)));
+        assert!(!visible.contains(&IVec2::new(5, 5)));
+    }
+
+    #[test]
+    fn only_sees_in_range() {
+        let (blockers, viewer) = level(&[
+            "############", //
+            "#@.........#",
+            "############",
+        ]);
+
+        let visible = visible_squares(&blockers, viewer, Vec2::X, 5., ALL_AROUND);
+
+        assert!(visible.contains(&IVec2::new(6, 1)));
+        assert!(!visible.contains(&IVec2::new(7, 1)));
+    }
+
+    #[test]
+    fn vision_is_symmetric() {
+        let mut rng = ChaCha8Rng::seed_from_u64(20);
+        for _ in 0..10 {
+            let blockers = random_level(&mut rng, 16, 0.25);
+            let floor = floor_squares(&blockers);
+            let sights: Vec<_> = floor
+                .iter()
+                .map(|square| visible_squares(&blockers, *square, Vec2::ZERO, 30., ALL_AROUND))
+                .collect();
+            for (a, seen_from_a) in floor.iter().zip(&sights) {
+                for (b, seen_from_b) in floor.iter().zip(&sights) {
+                    assert_eq!(
+                        seen_from_a.contains(b),
+                        seen_from_b.contains(a) || a == b,
+                        "{:?} and {:?}",
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+
+    /// Whether the line between two points crosses any part of the wall square centred on `wall`
+    fn crosses_square(from: Vec2, to: Vec2, wall: Vec2) -> bool {
+        let along = to - from;
+        let (mut enter, mut exit) = (0f32, 1f32);
+        for axis in 0..2 {
+            let (low, high) = (wall[axis] - 0.5, wall[axis] + 0.5);
+            if along[axis] == 0. {
+                if from[axis] < low || from[axis] > high {
+                    return false;
+                }
+                continue;
+            }
+            let a = (low - from[axis]) / along[axis];
+            let b = (high - from[axis]) / along[axis];
+            enter = enter.max(a.min(b));
+            exit = exit.min(a.max(b));
+        }
+        enter <= exit
+    }
+
+    /// Whether the line between two points passes through the middle of the wall square centred on `wall`, well
+    /// clear of its corners
+    fn crosses_middle(from: Vec2, to: Vec2, wall: Vec2) -> bool {
+        let along = to - from;
+        let mut closest = vec![0., 1.];
+        for axis in 0..2 {
+            if along[axis] != 0. {
+                closest.push((wall[axis] - from[axis]) / along[axis]);
+            }
+        }
+        closest
+            .into_iter()
+            .filter(|t| (0. ..=1.).contains(t))
+            .any(|t| {
+                let offset = from + along * t - wall;
+                offset.x.abs() + offset.y.abs() < 0.5 - 1e-3
+            })
+    }
+
+    /// Floor squares in plain sight are always seen, and ones behind the middle of a wall never are. Lines that only
+    /// clip the corner of a wall are left to the shadowcasting.
+    #[test]
+    fn sees_what_has_a_clear_line_and_nothing_behind_walls() {
+        let mut rng = ChaCha8Rng::seed_from_u64(20);
+        for _ in 0..10 {
+            let blockers = random_level(&mut rng, 24, 0.2);
+            let floor = floor_squares(&blockers);
+            let walls: Vec<_> = blockers
+                .vision_blocker_locations
+                .iter()
+                .map(|wall| Vec2::new(wall.x as f32, wall.y as f32))
+                .collect();
+            for _ in 0..10 {
+                let viewer = floor[rng.gen_range(0..floor.len())];
+                let visible = visible_squares(&blockers, viewer, Vec2::X, 30., ALL_AROUND);
+                for square in &floor {
+                    if (*square - viewer).as_vec2().length() <= 1.0 {
+                        continue;
+                    }
+                    let (from, to) = (viewer.as_vec2(), square.as_vec2());
+                    if !walls.iter().any(|wall| crosses_square(from, to, *wall)) {
+                        assert!(
+                            visible.contains(square),
+                            "{:?} hidden from {:?}",
+                            square,
+                            viewer
+                        );
+                    }
+                    if walls.iter().any(|wall| crosses_middle(from, to, *wall)) {
+                        assert!(
+                            !visible.contains(square),
+                            "{:?} seen from {:?}",
+                            square,
+                            viewer
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture shadowcasting` to compare how long each takes
+    #[test]
+    #[ignore]
+    fn benchmark_against_ray_casting() {
+        let mut rng = ChaCha8Rng::seed_from_u64(20);
+        let blockers = random_level(&mut rng, 64, 0.15);
+        let floor = floor_squares(&blockers);
+        let viewers: Vec<_> = (0..200)
+            .map(|_| floor[rng.gen_range(0..floor.len())])
+            .collect();
+
+        for (name, range, field_of_view_radians) in [
+            ("player", 30., ALL_AROUND),
+            ("patrol", 5., FRAC_PI_4),
+            ("sniper at the last threat level", 30., PI / 8.),
+        ] {
+            let mut seen = [0, 0];
+            let mut took = [0., 0.];
+            for (i, find) in [ray_cast_visible_squares, visible_squares]
+                .into_iter()
+                .enumerate()
+            {
+                let start = Instant::now();
+                for viewer in &viewers {
+                    seen[i] +=
+                        find(&blockers, *viewer, Vec2::X, range, field_of_view_radians).len();
+                }
+                took[i] = start.elapsed().as_secs_f64() * 1e6 / viewers.len() as f64;
+            }
+            println!(
+                "{}: ray casting {:.1}µs and {} squares, shadowcasting {:.1}µs and {} squares per viewer",
+                name,
+                took[0],
+                seen[0] / viewers.len(),
+                took[1],
+                seen[1] / viewers.len()
+            );
+        }
+    }
+}
@@ -3,23 +3,23 @@ use std::f32::consts;
 use bevy::prelude::*;
 use bevy::utils::HashSet;
 
-use crate::game::grid::grid_layout::GridLayout;
 use crate::game::grid::GridPosition;
-use crate::game::line_of_sight::FacingWallsCache;
+use crate::game::line_of_sight::shadowcasting;
+use crate::game::spawn::level::LevelVisionBlockers;
 use crate::game::threat::{ThreatTimer, ThreatTimerSettings};
-use crate::geometry_2d::line_segment::LineSegment;
 use crate::AppSet;
 
 pub fn plugin(app: &mut App) {
     // systems
     app.add_systems(
-        Update,
+        FixedUpdate,
         update_visible_squares.in_set(AppSet::Update), //.before(crate::game::spawn::enemy::follow_player),
     );
 
     // reflect
     app.register_type::<Facing>();
     app.register_type::<VisionAbility>();
+    app.register_type::<VisionArchetype>();
 }
 
 #[derive(Bundle, Default, Clone)]
@@ -27,7 +27,6 @@ pub struct VisionBundle {
     pub facing: Facing,
     pub vision_ability: VisionAbility,
     pub visible_squares: VisibleSquares,
-    pub facing_walls_cache: FacingWallsCache,
 }
 
 /// Which direction an enemy is looking
@@ -76,8 +75,9 @@ impl VisionAbility {
     }
 }
 
-// maybe we can figure out a way to encode these in LDTK for easy enemy design
-#[derive(Default)]
+// enemies pick theirs with the Archetype enum field in LDTK
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
 pub enum VisionArchetype {
     /// Very narrow FOV, Long range, short detection time
     Sniper,
@@ -110,18 +110,12 @@ impl VisibleSquares {
 }
 
 pub fn update_visible_squares(
-    mut query: Query<(
-        &GridPosition,
-        &VisionAbility,
-        &Facing,
-        &FacingWallsCache,
-        &mut VisibleSquares,
-    )>,
+    mut query: Query<(&GridPosition, &VisionAbility, &Facing, &mut VisibleSquares)>,
     threat_timer: Res<ThreatTimer>,
     threat_settings: Res<ThreatTimerSettings>,
-    grid: Res<GridLayout>,
+    blockers: Res<LevelVisionBlockers>,
 ) {
-    for (grid_position, vision, facing, walls, mut visible_squares) in query.iter_mut() {
+    for (grid_position, vision, facing, mut visible_squares) in query.iter_mut() {
         // don't recompute if grid coordinates haven't changed and if not immediately after threat level has changed TODO change this to work off a next threat level trigger
         let ray_start = grid_position.coordinates;
         let vision_range_threat_adjusted =
@@ -139,51 +133,12 @@ pub fn update_visible_squares(
         }
         //visible_squares.for_position = *grid_position;
 
-        let mut new_squares = vec![];
-
-        // iterate all squares in range and determine if they're visible or not
-        let bb = grid.bounding_box(grid_position, vision_range_threat_adjusted);
-        for ray_end in bb.coords_range() {
-            // info!("{:?} -> {:?}", ray_start, ray_end);
-
-            // too near
-            if ray_end.distance(ray_start) <= 1.0 {
-                if grid_position.coordinates + facing.0 == ray_end {
-                    new_squares.push(ray_end);
-                }
-                continue;
-            }
-
-            // too far
-            if ray_start.distance(ray_end) > vision_range_threat_adjusted {
-                continue;
-            }
-
-            // angle too wide
-            let ray = LineSegment::new(
-                grid.grid_to_world(&GridPosition::new(ray_start.x, ray_start.y)),
-                grid.grid_to_world(&GridPosition::new(ray_end.x, ray_end.y)),
-            );
-            // let ray = LineSegment::new(ray_start, ray_end);
-            let angle = ray.segment2d.direction.angle_between(facing.0);
-            if angle.abs() > vision.field_of_view_radians {
-                continue;
-            }
-
-            // wall in the way
-            let wall_in_the_way = walls.facing_wall_edges.iter().any(|w| ray.do_intersect(w));
-            if wall_in_the_way {
-                continue;
-            }
-
-            // we made it! the square is visible
-            new_squares.push(ray_end);
-        }
-
-        visible_squares.visible_squares = HashSet::from_iter(
-            new_squares
-                .into_iter()
-                .map(|v| IVec2::new(v.x as i32, v.y as i32)),
+        visible_squares.visible_squares = shadowcasting::visible_squares(
+            &blockers,
+            ray_start.as_ivec2(),
+            facing.0,
+            vision_range_threat_adjusted,
+            vision.field_of_view_radians,
         );
     }
 }
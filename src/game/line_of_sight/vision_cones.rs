@@ -1,130 +1,199 @@
-use std::collections::{HashMap, HashSet};
+//! Draws what each enemy can see, as one mesh per enemy rebuilt from its visible squares, tinted by what kind of
+//! enemy it is and how alert it is.
 
 use bevy::app::App;
 use bevy::prelude::*;
-use bevy::sprite::MaterialMesh2dBundle;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::utils::HashSet;
 
-use crate::AppSet;
+use crate::game::ai::{AiState, HasAiState};
 use crate::game::grid::grid_layout::GridLayout;
 use crate::game::grid::GridPosition;
-use crate::game::line_of_sight::vision::VisibleSquares;
-use crate::game::line_of_sight::vision_cones::handles::VisionConeRenderingHandles;
+use crate::game::line_of_sight::vision::{VisibleSquares, VisionArchetype};
+use crate::AppSet;
 
 pub fn plugin(app: &mut App) {
-    // plugins
-    app.add_plugins(handles::plugin);
-
     // systems
-    app.add_systems(Startup, setup);
-    app.add_systems(Update, draw_line_of_sight.in_set(AppSet::UpdateFog));
+    app.add_systems(Update, update_vision_cones.in_set(AppSet::UpdateFog));
+    app.observe(spawn_vision_cone);
+    app.observe(despawn_vision_cone);
 
     // reflection
-    app.register_type::<IlluminatedSquares>();
+    app.register_type::<VisionCone>();
 }
 
+/// Above the level, below the fog
+const VISION_CONE_Z: f32 = 7.;
+
 #[derive(Component, Clone, Copy, Default)]
 pub struct RenderedFieldOfView;
 
-#[derive(Component, Clone, Default, Reflect)]
+/// The mesh showing what one viewer can see. It's kept apart from the viewer, so it stays put in the world while
+/// they move.
+#[derive(Component, Reflect)]
 #[reflect(Component)]
-pub struct IlluminatedSquares {
-    pub squares: HashMap<IVec2, Entity>,
+pub struct VisionCone {
+    pub viewer: Entity,
+}
+
+/// Yellow for patrols and amber for snipers while they go about their business, turning red as they close in
+pub fn cone_color(archetype: VisionArchetype, ai_state: AiState) -> Color {
+    let calm = match archetype {
+        VisionArchetype::Sniper => Srgba::new(1.0, 0.7, 0.1, 0.06),
+        _ => Srgba::new(1.0, 1.0, 0.0, 0.05),
+    };
+    let alarmed = Srgba::new(1.0, 0.1, 0.1, 0.08);
+    let alertness = match ai_state {
+        AiState::Chasing => 1.0,
+        AiState::Suspicious | AiState::Searching => 0.5,
+        _ => 0.0,
+    };
+    Color::Srgba(calm.mix(&alarmed, alertness))
 }
 
-pub fn setup(mut commands: Commands) {
+fn spawn_vision_cone(
+    trigger: Trigger<OnAdd, RenderedFieldOfView>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    build_cone_mesh(&mut mesh, &HashSet::new(), &GridLayout::default());
     commands.spawn((
-        Name::new("IlluminatedSquares"),
-        IlluminatedSquares::default(),
-        SpatialBundle {
-            transform: Transform::from_translation(Vec3::default().with_z(7.)),
+        Name::new("VisionCone"),
+        VisionCone {
+            viewer: trigger.entity(),
+        },
+        MaterialMesh2dBundle {
+            mesh: meshes.add(mesh).into(),
+            material: materials.add(cone_color(VisionArchetype::Patrol, AiState::Idle)),
+            transform: Transform::from_translation(Vec3::Z * VISION_CONE_Z),
+            visibility: Visibility::Hidden,
             ..default()
         },
     ));
 }
 
-pub fn draw_line_of_sight(
+fn despawn_vision_cone(
+    trigger: Trigger<OnRemove, RenderedFieldOfView>,
+    cones: Query<(Entity, &VisionCone)>,
     mut commands: Commands,
-    visible_query: Query<&VisibleSquares, With<RenderedFieldOfView>>,
-    mut illuminated_query: Query<(Entity, &mut IlluminatedSquares)>,
-    grid: Res<GridLayout>,
-    handles: Res<VisionConeRenderingHandles>,
 ) {
-    let Ok((overlay_entity, mut overlay)) = illuminated_query.get_single_mut() else {
-        return;
-    };
-
-    let mut visible_squares = HashSet::<IVec2>::new();
-    for h in visible_query.iter() {
-        for xy in h.visible_squares.iter() {
-            visible_squares.insert(*xy);
+    for (cone_entity, cone) in &cones {
+        if cone.viewer == trigger.entity() {
+            commands.entity(cone_entity).despawn();
         }
     }
+}
 
-    let illuminated_positions = HashSet::from_iter(overlay.squares.keys().copied());
+pub fn update_vision_cones(
+    viewers: Query<
+        (
+            Ref<VisibleSquares>,
+            Option<&VisionArchetype>,
+            Option<&HasAiState>,
+        ),
+        With<RenderedFieldOfView>,
+    >,
+    mut cones: Query<(
+        &VisionCone,
+        &Mesh2dHandle,
+        &Handle<ColorMaterial>,
+        &mut Visibility,
+    )>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    grid: Res<GridLayout>,
+) {
+    for (cone, mesh_handle, material_handle, mut visibility) in &mut cones {
+        let Ok((visible, archetype, ai)) = viewers.get(cone.viewer) else {
+            continue;
+        };
 
-    let squares_to_remove = illuminated_positions.difference(&visible_squares);
-    for s in squares_to_remove.into_iter() {
-        // info!("Square {:?} is no longer visible", &s);
-        let Some(e) = overlay.squares.get(s) else {
+        let color = cone_color(
+            archetype.copied().unwrap_or(VisionArchetype::Patrol),
+            ai.map_or(AiState::Idle, |ai| ai.current_state),
+        );
+        if materials
+            .get(material_handle)
+            .is_some_and(|material| material.color != color)
+        {
+            if let Some(material) = materials.get_mut(material_handle) {
+                material.color = color;
+            }
+        }
+
+        if !visible.is_changed() {
             continue;
+        }
+        if let Some(mesh) = meshes.get_mut(&mesh_handle.0) {
+            build_cone_mesh(mesh, &visible.visible_squares, &grid);
+        }
+        *visibility = if visible.visible_squares.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
         };
-        commands.entity(*e).despawn();
-        overlay.squares.remove(s);
     }
+}
 
-    let squares_to_add = visible_squares.difference(&illuminated_positions);
-    for s in squares_to_add.into_iter() {
-        // info!("Square {:?} is visible!", &s);
-        let position = GridPosition::from_ivec(s);
-        let transform = grid.grid_to_world(&position);
-        let child_id = commands
-            .spawn((MaterialMesh2dBundle {
-                mesh: handles.mesh.clone().clone().into(),
-                material: handles.material.clone(),
-                transform: Transform::from_translation(transform.extend(0.0)),
-                ..default()
-            },))
-            .id();
-        overlay.squares.insert(*s, child_id);
-        commands.entity(overlay_entity).add_child(child_id);
+/// One square of mesh over each visible square, in world coordinates
+fn build_cone_mesh(mesh: &mut Mesh, squares: &HashSet<IVec2>, grid: &GridLayout) {
+    let half_square = grid.square_size / 2.;
+    let corners = [
+        Vec2::new(-half_square, -half_square),
+        Vec2::new(half_square, -half_square),
+        Vec2::new(half_square, half_square),
+        Vec2::new(-half_square, half_square),
+    ];
+
+    let mut positions = Vec::with_capacity(squares.len() * 4);
+    let mut indices = Vec::with_capacity(squares.len() * 6);
+    for square in squares {
+        let center = grid.grid_to_world(&GridPosition::from_ivec(square));
+        let first = positions.len() as u32;
+        positions.extend(corners.map(|corner| (center + corner).extend(0.).to_array()));
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
     }
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_indices(Indices::U32(indices));
 }
 
-/// Cache handles to a mesh/material that is instanced and used to render all cones of vision to save on performance vs. making new sprites for each one.
-mod handles {
-    use bevy::prelude::*;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    use crate::game::spawn::level::GRID_SIZE;
+    #[test]
+    fn one_quad_per_visible_square() {
+        let grid = GridLayout::new(10, 10);
+        let squares = HashSet::from_iter([IVec2::new(1, 1), IVec2::new(2, 1)]);
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
 
-    pub fn plugin(app: &mut App) {
-        app.init_resource::<VisionConeRenderingHandles>();
+        build_cone_mesh(&mut mesh, &squares, &grid);
 
-        app.register_type::<VisionConeRenderingHandles>();
+        assert_eq!(mesh.count_vertices(), 8);
+        assert_eq!(mesh.indices().map(Indices::len), Some(12));
     }
 
-    #[derive(Resource, Reflect)]
-    #[reflect(Resource)]
-    pub struct VisionConeRenderingHandles {
-        pub mesh: Handle<Mesh>,
-        pub material: Handle<ColorMaterial>,
-    }
-
-    impl FromWorld for VisionConeRenderingHandles {
-        // called when init_resource is used on the app
-        fn from_world(world: &mut World) -> Self {
-            // meshes
-            let mut meshes = world.resource_mut::<Assets<Mesh>>();
-            let grid_size = GRID_SIZE as f32;
-            let mesh_handle = meshes.add(Rectangle::new(grid_size, grid_size));
-
-            // materials
-            let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
-            let material = materials.add(ColorMaterial::from(Color::srgba(1.0, 1.0, 0.0, 0.05)));
-
-            VisionConeRenderingHandles {
-                mesh: mesh_handle,
-                material,
-            }
-        }
+    #[test]
+    fn chasing_turns_the_cone_red() {
+        let patrolling = cone_color(VisionArchetype::Patrol, AiState::Patrolling).to_srgba();
+        let chasing = cone_color(VisionArchetype::Patrol, AiState::Chasing).to_srgba();
+
+        assert!(patrolling.green > 0.9, "{:?}", patrolling);
+        assert!(chasing.green < 0.2, "{:?}", chasing);
+        assert_ne!(
+            cone_color(VisionArchetype::Sniper, AiState::Patrolling),
+            cone_color(VisionArchetype::Patrol, AiState::Patrolling)
+        );
     }
 }
@@ -7,11 +7,15 @@ mod animation;
 pub mod assets;
 pub mod audio;
 mod camera;
+pub mod campaign;
 pub mod end_game;
-mod ghost;
+pub mod ghost;
 pub mod grid;
 pub mod line_of_sight;
 pub mod movement;
+pub mod rng;
+pub mod session;
+pub mod snapshot;
 pub mod spawn;
 pub mod threat;
 
@@ -26,9 +30,14 @@ pub(super) fn plugin(app: &mut App) {
         spawn::plugin,
         grid::plugin,
         camera::plugin,
+        campaign::plugin,
+        end_game::plugin,
         line_of_sight::plugin,
         ghost::plugin,
         threat::plugin,
         dialog::plugin,
+        snapshot::plugin,
+        session::plugin,
+        rng::plugin,
     ));
 }
@@ -11,20 +11,17 @@ use crate::game::spawn::player::Player;
 use crate::input::PlayerAction;
 use crate::AppSet;
 
-use super::line_of_sight::front_facing_edges::RebuildCache;
-use super::spawn::health::OnDeath;
-
 pub fn plugin(app: &mut App) {
-    app.add_systems(Update, update_roll_timer.in_set(AppSet::TickTimers));
+    app.add_systems(FixedUpdate, update_roll_timer.in_set(AppSet::TickTimers));
     app.add_systems(
-        Update,
+        FixedUpdate,
         (respond_to_input, apply_movement)
             .chain()
             .in_set(AppSet::UpdateVirtualGrid),
     );
 
     app.register_type::<GridMovement>();
-    app.observe(reset_roll_timer_on_death);
+    app.register_type::<RollState>();
 }
 
 #[derive(Component, Reflect, Debug, Copy, Clone, PartialEq)]
@@ -110,6 +107,12 @@ pub fn respond_to_input(mut query: Query<(&ActionState<PlayerAction>, &mut GridM
         // Normalize so that diagonal movement has the same speed as horizontal and vertical movement.
         let intent = intent.normalize_or_zero();
 
+        // Sticks push as hard as they're tilted, so a gentle tilt sneaks along slowly
+        let analog = action_state
+            .clamped_axis_pair(&PlayerAction::Move)
+            .map_or(Vec2::ZERO, |axis| axis.xy().clamp_length_max(1.0));
+        let intent = if analog != Vec2::ZERO { analog } else { intent };
+
         movement.acceleration_player_force = intent * movement.acceleration_player_multiplier;
     }
 }
@@ -118,12 +121,9 @@ pub fn apply_movement(
     mut query: Query<(&mut GridPosition, &mut GridMovement, Option<&RollState>)>,
     time: Res<Time>,
     walls: Res<LevelWalls>,
-    mut commands: Commands,
 ) {
     let dt = time.delta_seconds();
     for (mut position, mut movement, maybe_roll) in query.iter_mut() {
-        let prev_x = position.coordinates.x;
-        let prev_y = position.coordinates.y;
         let force = movement.current_force() * dt; // scale it by time
 
         // apply forces and friction
@@ -192,10 +192,6 @@ pub fn apply_movement(
         // apply the movement to our actual position
         position.coordinates = next_pos.coordinates;
         position.offset = next_pos.offset;
-
-        if prev_x != position.coordinates.x || prev_y != position.coordinates.y {
-            commands.trigger(RebuildCache);
-        }
     }
 }
 
@@ -241,18 +237,3 @@ fn update_roll_timer(
         }
     }
 }
-
-fn reset_roll_timer_on_death(
-    _trigger: Trigger<OnDeath>,
-    mut query: Query<(&mut RollState, &mut GridMovement)>,
-) {
-    for (mut roll, mut movement) in &mut query {
-        let timer_duration = roll.timer.duration();
-        roll.timer.tick(timer_duration);
-
-        let cooldown_duration = roll.cooldown.duration();
-        roll.cooldown.tick(cooldown_duration);
-
-        movement.is_rolling = false;
-    }
-}
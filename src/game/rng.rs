@@ -0,0 +1,120 @@
+//! All gameplay randomness is drawn from here, seeded from the [`SessionSeed`], so the same seed always plays out
+//! the same way. Each subsystem draws from its own stream, so e.g. an extra footstep sound doesn't change which
+//! dialog line comes up next. The streams start over from the seed whenever a level spawns, which keeps recorded
+//! sessions self-contained, and whenever the player dies, along with the rest of the level.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::LevelEvent;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::game::session::SessionSeed;
+use crate::game::spawn::health::OnDeath;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<GameRng>();
+
+    // systems
+    app.add_systems(Update, reseed_on_level_spawn);
+    app.observe(reseed_on_death);
+}
+
+/// The subsystems with their own stream of randomness
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Dialog,
+    Sfx,
+    Ai,
+}
+
+impl RngStream {
+    fn id(self) -> u64 {
+        match self {
+            Self::Dialog => 0,
+            Self::Sfx => 1,
+            Self::Ai => 2,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// The random number generator for one subsystem, picking up where it left off
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(stream.id());
+            rng
+        })
+    }
+}
+
+fn reseed_on_level_spawn(
+    mut level_events: EventReader<LevelEvent>,
+    seed: Res<SessionSeed>,
+    mut rng: ResMut<GameRng>,
+) {
+    let level_spawned = level_events
+        .read()
+        .any(|level_event| matches!(level_event, LevelEvent::Transformed(_)));
+    if level_spawned || seed.is_changed() {
+        *rng = GameRng::new(seed.0);
+    }
+}
+
+fn reseed_on_death(_trigger: Trigger<OnDeath>, seed: Res<SessionSeed>, mut rng: ResMut<GameRng>) {
+    *rng = GameRng::new(seed.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn draws(rng: &mut GameRng, stream: RngStream) -> Vec<u32> {
+        (0..8).map(|_| rng.stream(stream).gen()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_numbers() {
+        assert_eq!(
+            draws(&mut GameRng::new(7), RngStream::Dialog),
+            draws(&mut GameRng::new(7), RngStream::Dialog)
+        );
+        assert_ne!(
+            draws(&mut GameRng::new(7), RngStream::Dialog),
+            draws(&mut GameRng::new(8), RngStream::Dialog)
+        );
+    }
+
+    #[test]
+    fn streams_dont_affect_each_other() {
+        let mut untouched = GameRng::new(7);
+        let mut busy = GameRng::new(7);
+        draws(&mut busy, RngStream::Sfx);
+
+        assert_eq!(
+            draws(&mut untouched, RngStream::Dialog),
+            draws(&mut busy, RngStream::Dialog)
+        );
+        assert_ne!(
+            draws(&mut GameRng::new(7), RngStream::Sfx),
+            draws(&mut GameRng::new(7), RngStream::Dialog)
+        );
+    }
+}
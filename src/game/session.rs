@@ -0,0 +1,456 @@
+//! Records the player's input on every fixed tick, so a playtester's session can be played back exactly to
+//! reproduce a bug. Separate from ghosts, which only record where the player went.
+//!
+//! Start the game with `--record <file>` to record, and `--replay <file>` to play a recording back, adding
+//! `--headless` to do that without a window. A recording covers the whole run, in a segment for every level that
+//! spawns, and is saved whenever the player dies, finishes a level, or quits. Each segment is played back from when
+//! its level spawns, so loading taking longer or shorter doesn't put the rest of the replay out of step.
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_ecs_ldtk::prelude::RawLevelAccessor;
+use bevy_ecs_ldtk::{LevelEvent, LevelSelection};
+use leafwing_input_manager::axislike::DualAxisData;
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+use crate::game::campaign::{Campaign, LevelCompleted, LevelStarted, RestartLevel};
+use crate::game::ghost::tick_rate_hz;
+use crate::game::spawn::health::OnDeath;
+use crate::game::spawn::player::Player;
+use crate::input::PlayerAction;
+use crate::screen::{PlayState, Screen};
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(SessionSeed(rand::random()));
+
+    // systems
+    app.add_systems(
+        Update,
+        (
+            go_to_replayed_level.run_if(in_state(Screen::Playing)),
+            start_session_on_level_spawn,
+        )
+            .chain(),
+    );
+    app.add_systems(
+        FixedPreUpdate,
+        (replay_input, record_input)
+            .chain()
+            .run_if(in_state(PlayState::Running)),
+    );
+    app.add_systems(Last, save_recording_on_exit);
+    app.observe(save_recording_on::<OnDeath>);
+    app.observe(save_recording_on::<LevelCompleted>);
+}
+
+pub const SESSION_FILE_VERSION: u16 = 2;
+
+/// The seed every bit of gameplay randomness in the session is drawn from
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionSeed(pub u64);
+
+/// Everything needed to play a session back: the seed its randomness came from, and the input on every tick of
+/// every level played
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionRecording {
+    pub version: u16,
+    pub seed: u64,
+    pub tick_rate_hz: u16,
+    /// One for every time a level spawned, in the order they were played
+    pub segments: Vec<LevelSegment>,
+}
+
+/// The input from when a level spawned until the next one did
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LevelSegment {
+    /// The LDtk iid of the level
+    pub level_id: String,
+    pub ticks: Vec<TickInput>,
+}
+
+/// The player's input on one fixed tick
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TickInput {
+    /// Every action held down
+    pub pressed: Vec<PlayerAction>,
+    /// Where the movement stick was pushed, if anywhere
+    pub movement: Option<(f32, f32)>,
+}
+
+impl TickInput {
+    fn read(action_state: &ActionState<PlayerAction>) -> Self {
+        Self {
+            pressed: action_state.get_pressed(),
+            movement: action_state
+                .axis_pair(&PlayerAction::Move)
+                .map(|axis| (axis.x(), axis.y())),
+        }
+    }
+
+    fn apply(&self, action_state: &mut ActionState<PlayerAction>) {
+        for action in action_state.keys() {
+            if !self.pressed.contains(&action) {
+                action_state.release(&action);
+            }
+        }
+        for action in &self.pressed {
+            action_state.press(action);
+        }
+        action_state
+            .action_data_mut_or_default(&PlayerAction::Move)
+            .axis_pair = self.movement.map(|(x, y)| DualAxisData::new(x, y));
+    }
+}
+
+impl SessionRecording {
+    /// Ticks recorded from now on go to `level_id`
+    fn start_level(&mut self, level_id: String) {
+        self.segments.push(LevelSegment {
+            level_id,
+            ticks: vec![],
+        });
+    }
+
+    /// Adds a tick to the level being played, if one has spawned yet
+    fn record(&mut self, input: TickInput) {
+        if let Some(segment) = self.segments.last_mut() {
+            segment.ticks.push(input);
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_ron(&contents)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let contents = ron::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    fn from_ron(contents: &str) -> Result<Self, String> {
+        let recording: Self = ron::from_str(contents).map_err(|e| e.to_string())?;
+        if recording.version != SESSION_FILE_VERSION {
+            return Err(format!(
+                "unsupported session file version {}",
+                recording.version
+            ));
+        }
+        Ok(recording)
+    }
+}
+
+/// Records the session to a file, see [`record_to`]
+#[derive(Resource, Debug)]
+struct SessionRecorder {
+    path: PathBuf,
+    /// Nothing's recorded until the first level has spawned
+    recording: Option<SessionRecording>,
+}
+
+/// Plays a recorded session back, see [`replay_from`]
+#[derive(Resource, Debug)]
+struct SessionReplay {
+    recording: SessionRecording,
+    /// Set once the campaign has been moved to the level the recording starts in
+    level_selected: bool,
+    /// The segment being played, or to play next when its level spawns
+    segment: usize,
+    /// The tick of the segment to play next, once its level has spawned
+    next_tick: Option<usize>,
+    exit_when_done: bool,
+}
+
+/// Records the player's input to `path` from the next level that spawns on
+pub fn record_to(app: &mut App, path: PathBuf) {
+    app.insert_resource(SessionRecorder {
+        path,
+        recording: None,
+    });
+}
+
+/// Plays back the session recorded in `path` instead of the player's input, quitting afterwards if `exit_when_done`
+pub fn replay_from(app: &mut App, path: &Path, exit_when_done: bool) {
+    match SessionRecording::read(path) {
+        Ok(recording) => {
+            info!(
+                "Replaying {} levels from {}",
+                recording.segments.len(),
+                path.display()
+            );
+            app.insert_resource(SessionSeed(recording.seed));
+            app.insert_resource(SessionReplay {
+                recording,
+                level_selected: false,
+                segment: 0,
+                next_tick: None,
+                exit_when_done,
+            });
+        }
+        Err(e) => {
+            error!("Couldn't replay {}: {}", path.display(), e);
+            app.world_mut().send_event(AppExit::error());
+        }
+    }
+}
+
+/// The campaign always starts at the first level, so skip ahead to the fresh start of the one the recording started in
+fn go_to_replayed_level(
+    replay: Option<ResMut<SessionReplay>>,
+    ldtk_project_entities: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut campaign: ResMut<Campaign>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut commands: Commands,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+    if replay.level_selected {
+        return;
+    }
+    let Some(project) = ldtk_project_entities
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+    else {
+        return;
+    };
+    replay.level_selected = true;
+    let Some(first_level) = replay.recording.segments.first() else {
+        info!("The recording is empty, there's nothing to replay");
+        commands.remove_resource::<SessionReplay>();
+        return;
+    };
+    let Some(index) = project
+        .iter_raw_levels()
+        .position(|level| level.iid == first_level.level_id)
+    else {
+        error!(
+            "The recorded level {} isn't in this version of the game",
+            first_level.level_id
+        );
+        commands.remove_resource::<SessionReplay>();
+        return;
+    };
+    if index == campaign.current_level {
+        // it may have spawned before the replay could start, so start it over
+        commands.trigger(RestartLevel);
+    } else {
+        campaign.current_level = index;
+        *level_selection = LevelSelection::index(index);
+        commands.trigger(LevelStarted(index));
+    }
+}
+
+/// Recording and replaying both start from when a level has finished spawning, so they line up tick for tick
+fn start_session_on_level_spawn(
+    mut level_events: EventReader<LevelEvent>,
+    recorder: Option<ResMut<SessionRecorder>>,
+    replay: Option<ResMut<SessionReplay>>,
+    seed: Res<SessionSeed>,
+    time: Res<Time<Fixed>>,
+) {
+    let Some(level_iid) = level_events
+        .read()
+        .find_map(|level_event| match level_event {
+            LevelEvent::Transformed(level_iid) => Some(level_iid),
+            _ => None,
+        })
+    else {
+        return;
+    };
+
+    if let Some(mut recorder) = recorder {
+        recorder
+            .recording
+            .get_or_insert_with(|| SessionRecording {
+                version: SESSION_FILE_VERSION,
+                seed: seed.0,
+                tick_rate_hz: tick_rate_hz(&time),
+                segments: vec![],
+            })
+            .start_level(level_iid.get().clone());
+    }
+
+    if let Some(mut replay) = replay {
+        let next_level = replay
+            .recording
+            .segments
+            .get(replay.segment)
+            .map(|segment| &segment.level_id);
+        if replay.level_selected
+            && replay.next_tick.is_none()
+            && next_level == Some(level_iid.get())
+        {
+            if replay.recording.tick_rate_hz != tick_rate_hz(&time) {
+                warn!(
+                    "The session was recorded at {} Hz but the game runs at {} Hz, it won't replay the same",
+                    replay.recording.tick_rate_hz,
+                    tick_rate_hz(&time)
+                );
+            }
+            replay.next_tick = Some(0);
+        }
+    }
+}
+
+fn replay_input(
+    replay: Option<ResMut<SessionReplay>>,
+    mut player: Query<&mut ActionState<PlayerAction>, With<Player>>,
+    mut exit: EventWriter<AppExit>,
+    mut commands: Commands,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+    let Some(tick) = replay.next_tick else {
+        return;
+    };
+    let segment = &replay.recording.segments[replay.segment];
+    let Some(input) = segment.ticks.get(tick) else {
+        // the next level should be spawning now, its segment starts once it has
+        replay.segment += 1;
+        replay.next_tick = None;
+        if replay.segment == replay.recording.segments.len() {
+            info!("Finished replaying the session");
+            if replay.exit_when_done {
+                exit.send(AppExit::Success);
+            }
+            commands.remove_resource::<SessionReplay>();
+        }
+        return;
+    };
+    for mut action_state in &mut player {
+        input.apply(&mut action_state);
+    }
+    replay.next_tick = Some(tick + 1);
+}
+
+fn record_input(
+    recorder: Option<ResMut<SessionRecorder>>,
+    player: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    let Some(recording) = &mut recorder.recording else {
+        return;
+    };
+    // still record a tick without a player, so the ticks keep lining up
+    let input = player.get_single().map(TickInput::read).unwrap_or_default();
+    recording.record(input);
+}
+
+fn save(recorder: &SessionRecorder) {
+    let Some(recording) = &recorder.recording else {
+        return;
+    };
+    if let Err(e) = recording.write(&recorder.path) {
+        warn!(
+            "Couldn't save the session to {}: {}",
+            recorder.path.display(),
+            e
+        );
+    }
+}
+
+fn save_recording_on<E: Event>(_trigger: Trigger<E>, recorder: Option<Res<SessionRecorder>>) {
+    if let Some(recorder) = recorder {
+        save(&recorder);
+    }
+}
+
+fn save_recording_on_exit(mut exits: EventReader<AppExit>, recorder: Option<Res<SessionRecorder>>) {
+    if exits.read().count() > 0 {
+        if let Some(recorder) = recorder {
+            save(&recorder);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(pressed: Vec<PlayerAction>, movement: Option<(f32, f32)>) -> TickInput {
+        TickInput { pressed, movement }
+    }
+
+    #[test]
+    fn round_trips_through_ron() {
+        let recording = SessionRecording {
+            version: SESSION_FILE_VERSION,
+            seed: 42,
+            tick_rate_hz: 60,
+            segments: vec![
+                LevelSegment {
+                    level_id: "a-level".to_string(),
+                    ticks: vec![
+                        TickInput::default(),
+                        held(vec![PlayerAction::MoveUp, PlayerAction::Roll], None),
+                    ],
+                },
+                LevelSegment {
+                    level_id: "another-level".to_string(),
+                    ticks: vec![held(vec![PlayerAction::Move], Some((0.5, -0.25)))],
+                },
+            ],
+        };
+        let contents = ron::to_string(&recording).unwrap();
+
+        assert_eq!(SessionRecording::from_ron(&contents), Ok(recording));
+    }
+
+    #[test]
+    fn refuses_other_versions() {
+        let recording = SessionRecording {
+            version: SESSION_FILE_VERSION + 1,
+            seed: 0,
+            tick_rate_hz: 60,
+            segments: vec![],
+        };
+        let contents = ron::to_string(&recording).unwrap();
+
+        assert!(SessionRecording::from_ron(&contents).is_err());
+    }
+
+    #[test]
+    fn keeps_earlier_levels_when_the_next_spawns() {
+        let mut recording = SessionRecording {
+            version: SESSION_FILE_VERSION,
+            seed: 0,
+            tick_rate_hz: 60,
+            segments: vec![],
+        };
+
+        recording.record(held(vec![PlayerAction::Roll], None));
+        recording.start_level("a-level".to_string());
+        recording.record(TickInput::default());
+        recording.record(held(vec![PlayerAction::MoveUp], None));
+        recording.start_level("another-level".to_string());
+        recording.record(TickInput::default());
+
+        let recorded: Vec<_> = recording
+            .segments
+            .iter()
+            .map(|segment| (segment.level_id.as_str(), segment.ticks.len()))
+            .collect();
+        assert_eq!(recorded, [("a-level", 2), ("another-level", 1)]);
+    }
+
+    #[test]
+    fn applied_input_reads_back_the_same() {
+        let mut action_state = ActionState::<PlayerAction>::default();
+        action_state.press(&PlayerAction::MoveLeft);
+
+        let input = held(vec![PlayerAction::Roll], Some((1.0, 0.0)));
+        input.apply(&mut action_state);
+
+        assert!(!action_state.pressed(&PlayerAction::MoveLeft));
+        assert_eq!(TickInput::read(&action_state), input);
+    }
+}
@@ -0,0 +1,304 @@
+//! Rewinds the level to exactly how it was when it started, every time the player dies.
+//!
+//! Once a level has spawned, every reflected component from this module's siblings (plus [`Transform`] and
+//! [`Visibility`]) on the entities in the grid is copied into a [`LoopSnapshot`], along with every reflected
+//! resource from the same place. Dying puts all of it back: changed components are reset, components added since
+//! are removed, and removed ones come back. Ghosts are left alone, they carry over from one loop to the next.
+
+use std::any::TypeId;
+
+use bevy::ecs::reflect::{ReflectComponent, ReflectResource};
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::{GridCoords, LevelEvent};
+
+use crate::game::campaign::{Campaign, LevelStarted};
+use crate::game::end_game::{LoseConditions, RunStats};
+use crate::game::ghost::Ghost;
+use crate::game::grid::GridPosition;
+use crate::game::spawn::health::OnDeath;
+use crate::game::threat::PlayStopwatch;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<LoopSnapshot>();
+
+    // systems
+    app.add_systems(Update, snapshot_spawned_level);
+    app.observe(forget_snapshot);
+    app.observe(restore_snapshot_on_death);
+}
+
+/// Reflected types from under here are gameplay state
+const GAME_MODULE: &str = concat!(env!("CARGO_CRATE_NAME"), "::game::");
+
+/// Resources that keep going across loops, instead of being rewound
+fn kept_between_loops() -> [TypeId; 4] {
+    [
+        TypeId::of::<Campaign>(),
+        TypeId::of::<RunStats>(),
+        TypeId::of::<LoseConditions>(),
+        // the run's time counts every life, not just the last one
+        TypeId::of::<PlayStopwatch>(),
+    ]
+}
+
+/// The state of the level when it started
+#[derive(Resource, Default)]
+pub struct LoopSnapshot {
+    entities: Vec<EntitySnapshot>,
+    resources: Vec<(ReflectResource, Box<dyn Reflect>)>,
+}
+
+struct EntitySnapshot {
+    entity: Entity,
+    components: HashMap<TypeId, Box<dyn Reflect>>,
+}
+
+/// Every component type that gets rewound
+fn rewound_components(registry: &TypeRegistry) -> Vec<(TypeId, ReflectComponent)> {
+    let engine_components = [TypeId::of::<Transform>(), TypeId::of::<Visibility>()];
+    registry
+        .iter()
+        .filter(|registration| {
+            registration
+                .type_info()
+                .type_path()
+                .starts_with(GAME_MODULE)
+                || engine_components.contains(&registration.type_id())
+        })
+        .filter_map(|registration| {
+            let reflect_component = registration.data::<ReflectComponent>()?;
+            Some((registration.type_id(), reflect_component.clone()))
+        })
+        .collect()
+}
+
+/// Every resource type that gets rewound
+fn rewound_resources(registry: &TypeRegistry) -> Vec<ReflectResource> {
+    let kept = kept_between_loops();
+    registry
+        .iter()
+        .filter(|registration| {
+            registration
+                .type_info()
+                .type_path()
+                .starts_with(GAME_MODULE)
+                && !kept.contains(&registration.type_id())
+        })
+        .filter_map(|registration| registration.data::<ReflectResource>().cloned())
+        .collect()
+}
+
+/// LDtk has finished spawning and placing the level's entities a frame after it spawns the level,
+/// and everything has been turned into our own components by then
+fn snapshot_spawned_level(mut level_events: EventReader<LevelEvent>, mut commands: Commands) {
+    if level_events
+        .read()
+        .any(|level_event| matches!(level_event, LevelEvent::Transformed(_)))
+    {
+        commands.add(take_snapshot);
+    }
+}
+
+/// A snapshot of the previous level is no use in the next one
+fn forget_snapshot(_trigger: Trigger<LevelStarted>, mut snapshot: ResMut<LoopSnapshot>) {
+    *snapshot = LoopSnapshot::default();
+}
+
+fn restore_snapshot_on_death(_trigger: Trigger<OnDeath>, mut commands: Commands) {
+    // runs after the other observers have made their own changes, so the snapshot has the final say
+    commands.add(restore_snapshot);
+}
+
+pub fn take_snapshot(world: &mut World) {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let components = rewound_components(&registry);
+
+    let mut level_entities = world
+        .query_filtered::<Entity, (Or<(With<GridPosition>, With<GridCoords>)>, Without<Ghost>)>();
+    let entities = level_entities
+        .iter(world)
+        .map(|entity| {
+            let entity_ref = world.entity(entity);
+            EntitySnapshot {
+                entity,
+                components: components
+                    .iter()
+                    .filter_map(|(type_id, reflect_component)| {
+                        let component = reflect_component.reflect(entity_ref)?;
+                        Some((*type_id, component.clone_value()))
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let resources = rewound_resources(&registry)
+        .into_iter()
+        .filter_map(|reflect_resource| {
+            let resource = reflect_resource.reflect(world)?.clone_value();
+            Some((reflect_resource, resource))
+        })
+        .collect();
+
+    world.insert_resource(LoopSnapshot {
+        entities,
+        resources,
+    });
+}
+
+pub fn restore_snapshot(world: &mut World) {
+    world.resource_scope(|world, snapshot: Mut<LoopSnapshot>| {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let components = rewound_components(&registry);
+
+        for entity_snapshot in &snapshot.entities {
+            let Some(mut entity) = world.get_entity_mut(entity_snapshot.entity) else {
+                continue;
+            };
+            for (type_id, reflect_component) in &components {
+                let saved = entity_snapshot.components.get(type_id);
+                // `None` if the entity doesn't have the component now, otherwise whether it's the same as it was
+                let unchanged =
+                    reflect_component
+                        .reflect(EntityRef::from(&entity))
+                        .map(|current| {
+                            saved.and_then(|saved| current.reflect_partial_eq(saved.as_ref()))
+                                == Some(true)
+                        });
+                match (saved, unchanged) {
+                    // only touch what changed, so change detection stays quiet for everything else
+                    (Some(saved), Some(false)) => {
+                        reflect_component.apply(&mut entity, saved.as_ref());
+                    }
+                    (Some(saved), None) => {
+                        reflect_component.insert(&mut entity, saved.as_ref(), &registry);
+                    }
+                    (None, Some(_)) => reflect_component.remove(&mut entity),
+                    (Some(_), Some(true)) | (None, None) => {}
+                }
+            }
+        }
+
+        for (reflect_resource, saved) in &snapshot.resources {
+            let unchanged = reflect_resource
+                .reflect(world)
+                .and_then(|current| current.reflect_partial_eq(saved.as_ref()))
+                == Some(true);
+            if !unchanged {
+                reflect_resource.apply_or_insert(world, saved.as_ref(), &registry);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::Stopwatch;
+
+    use super::*;
+
+    #[derive(Component, Reflect, Debug, Default, PartialEq)]
+    #[reflect(Component)]
+    struct Counter(u32);
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Alerted;
+
+    #[derive(Resource, Reflect, Default)]
+    #[reflect(Resource)]
+    struct Doors(u32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        {
+            let registry = world.resource::<AppTypeRegistry>();
+            let mut registry = registry.write();
+            registry.register::<GridPosition>();
+            registry.register::<Counter>();
+            registry.register::<Alerted>();
+            registry.register::<Doors>();
+            registry.register::<Campaign>();
+            registry.register::<PlayStopwatch>();
+        }
+        world.init_resource::<Doors>();
+        world.init_resource::<Campaign>();
+        world.insert_resource(PlayStopwatch(Stopwatch::new()));
+        world
+    }
+
+    #[test]
+    fn rewinds_changed_added_and_removed_components() {
+        let mut world = world();
+        let entity = world.spawn((GridPosition::new(1., 2.), Counter(3))).id();
+        take_snapshot(&mut world);
+
+        world
+            .entity_mut(entity)
+            .insert((GridPosition::new(5., 5.), Alerted))
+            .remove::<Counter>();
+        restore_snapshot(&mut world);
+
+        assert_eq!(
+            world.get::<GridPosition>(entity),
+            Some(&GridPosition::new(1., 2.))
+        );
+        assert_eq!(world.get::<Counter>(entity), Some(&Counter(3)));
+        assert!(world.get::<Alerted>(entity).is_none());
+    }
+
+    #[test]
+    fn rewinds_resources_except_those_kept_between_loops() {
+        let mut world = world();
+        take_snapshot(&mut world);
+
+        world.resource_mut::<Doors>().0 = 2;
+        world.resource_mut::<Campaign>().current_level = 4;
+        world
+            .resource_mut::<PlayStopwatch>()
+            .0
+            .tick(Duration::from_secs(12));
+        restore_snapshot(&mut world);
+
+        assert_eq!(world.resource::<Doors>().0, 0);
+        assert_eq!(world.resource::<Campaign>().current_level, 4);
+        assert_eq!(
+            world.resource::<PlayStopwatch>().0.elapsed(),
+            Duration::from_secs(12)
+        );
+    }
+
+    #[test]
+    fn leaves_ghosts_alone() {
+        let mut world = world();
+        let ghost = world.spawn((Ghost, GridPosition::new(1., 2.))).id();
+        take_snapshot(&mut world);
+
+        world.entity_mut(ghost).insert(GridPosition::new(5., 5.));
+        restore_snapshot(&mut world);
+
+        assert_eq!(
+            world.get::<GridPosition>(ghost),
+            Some(&GridPosition::new(5., 5.))
+        );
+    }
+
+    #[test]
+    fn skips_entities_that_are_gone() {
+        let mut world = world();
+        let entity = world.spawn(GridPosition::new(1., 2.)).id();
+        take_snapshot(&mut world);
+
+        world.despawn(entity);
+        restore_snapshot(&mut world);
+
+        assert!(world.get_entity(entity).is_none());
+    }
+}
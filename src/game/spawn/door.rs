@@ -1,20 +1,34 @@
-use bevy::{prelude::*, render::primitives::Aabb};
+//! Doors open while something that can open them stands in the doorway, or while a linked pressure plate is held down.
+//! The player and their live ghosts can both open doors and hold plates, so a past life can keep a door open for you.
+
+use bevy::{prelude::*, render::primitives::Aabb, utils::HashSet};
+use bevy_ecs_ldtk::ldtk::ldtk_fields::{LdtkFields, LdtkFieldsError};
 use bevy_ecs_ldtk::prelude::*;
 
 use crate::{
     game::{line_of_sight::BlocksVision, utilities::intersect},
     screen::Screen,
+    AppSet,
 };
 
-use super::{level::BlocksMovement, player::Player};
+use super::level::BlocksMovement;
 
 pub fn plugin(app: &mut App) {
     // spawning
     app.register_ldtk_entity::<LdtkDoorBundle>("Door");
+    app.register_ldtk_entity::<LdtkPressurePlateBundle>("PressurePlate");
+    app.add_systems(Update, fix_loaded_ldtk_pressure_plates);
     app.add_systems(
-        Update,
-        (open_doors, close_doors).run_if(in_state(Screen::Playing)),
+        FixedUpdate,
+        (press_plates, update_doors)
+            .chain()
+            .run_if(in_state(Screen::Playing))
+            .in_set(AppSet::Collide),
     );
+
+    // reflection
+    app.register_type::<CanOpenDoors>();
+    app.register_type::<PressurePlate>();
 }
 
 #[derive(Component, Default, Copy, Clone)]
@@ -31,45 +45,127 @@ struct LdtkDoorBundle {
     vision: BlocksVision,
 }
 
-fn open_doors(
-    player_query: Query<(&Transform, &Aabb), With<Player>>,
-    mut door_query: Query<
-        (Entity, &mut Visibility, &Transform, &Aabb),
-        (With<LdtkDoor>, With<BlocksMovement>, With<BlocksVision>),
-    >,
+/// Opens doors by walking into them, and holds down pressure plates by standing on them
+#[derive(Component, Reflect, Debug, Default, Copy, Clone)]
+#[reflect(Component)]
+pub struct CanOpenDoors;
+
+#[derive(Component, Default, Copy, Clone)]
+struct LdtkPressurePlate;
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct LdtkPressurePlateBundle {
+    tag: LdtkPressurePlate,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[from_entity_instance]
+    entity_instance: EntityInstance,
+}
+
+/// Holds its linked doors open while pressed
+#[derive(Component, Reflect, Debug, Default, Clone)]
+#[reflect(Component)]
+pub struct PressurePlate {
+    /// LDtk iids of the linked doors
+    doors: Vec<String>,
+    pressed: bool,
+}
+
+const PLATE_COLOR: Color = Color::srgb(0.35, 0.35, 0.35);
+const PRESSED_PLATE_COLOR: Color = Color::srgb(0.75, 0.7, 0.4);
+
+/// Takes all ldtk pressure plate entities, and adds all the components we need for them to work in our game.
+fn fix_loaded_ldtk_pressure_plates(
+    query: Query<(Entity, &EntityInstance), With<LdtkPressurePlate>>,
     mut commands: Commands,
 ) {
-    let Ok(player) = player_query.get_single() else {
-        return;
-    };
+    for (ldtk_entity, instance) in query.iter() {
+        let doors = match instance.get_maybe_entity_refs_field("Doors") {
+            Ok(doors) => doors
+                .iter()
+                .flatten()
+                .map(|door| door.entity_iid.clone())
+                .collect(),
+            Err(LdtkFieldsError::FieldNotFound { .. }) => vec![],
+            Err(error) => {
+                warn!(
+                    "Pressure plate {} has a broken field: {}, it won't open anything",
+                    instance.iid, error
+                );
+                vec![]
+            }
+        };
+        commands
+            .entity(ldtk_entity)
+            .remove::<LdtkPressurePlate>() // we have to remove it because it's used as the query for this function
+            .insert((
+                Name::new("Pressure Plate"),
+                PressurePlate {
+                    doors,
+                    pressed: false,
+                },
+                Sprite {
+                    color: PLATE_COLOR,
+                    custom_size: Some(Vec2::splat(12.0)),
+                    ..default()
+                },
+                Handle::<Image>::default(),
+            ));
+    }
+}
 
-    for (entity, mut visibility, transform, aabb) in &mut door_query {
-        if intersect(player, (transform, aabb)) {
-            commands.entity(entity).remove::<BlocksMovement>();
-            commands.entity(entity).remove::<BlocksVision>();
-            *visibility = Visibility::Hidden;
+fn press_plates(
+    openers: Query<(&Transform, &Aabb), With<CanOpenDoors>>,
+    mut plates: Query<(&mut PressurePlate, &mut Sprite, &Transform, &Aabb)>,
+) {
+    for (mut plate, mut sprite, transform, aabb) in &mut plates {
+        let pressed = openers
+            .iter()
+            .any(|opener| intersect(opener, (transform, aabb)));
+        if plate.pressed != pressed {
+            plate.pressed = pressed;
+            sprite.color = if pressed {
+                PRESSED_PLATE_COLOR
+            } else {
+                PLATE_COLOR
+            };
         }
     }
 }
 
-fn close_doors(
-    player_query: Query<(&Transform, &Aabb), With<Player>>,
+/// Doors are open while someone stands in the doorway or a linked plate is pressed, and closed otherwise
+fn update_doors(
+    openers: Query<(&Transform, &Aabb), With<CanOpenDoors>>,
+    plates: Query<&PressurePlate>,
     mut door_query: Query<
-        (Entity, &mut Visibility, &Transform, &Aabb),
         (
-            With<LdtkDoor>,
-            Without<BlocksMovement>,
-            Without<BlocksVision>,
+            Entity,
+            &EntityIid,
+            &mut Visibility,
+            &Transform,
+            &Aabb,
+            Has<BlocksMovement>,
         ),
+        With<LdtkDoor>,
     >,
     mut commands: Commands,
 ) {
-    let Ok(player) = player_query.get_single() else {
-        return;
-    };
+    let held_open: HashSet<&str> = plates
+        .iter()
+        .filter(|plate| plate.pressed)
+        .flat_map(|plate| plate.doors.iter().map(String::as_str))
+        .collect();
 
-    for (entity, mut visibility, transform, aabb) in &mut door_query {
-        if !intersect(player, (transform, aabb)) {
+    for (entity, iid, mut visibility, transform, aabb, closed) in &mut door_query {
+        let open = held_open.contains(iid.as_str())
+            || openers
+                .iter()
+                .any(|opener| intersect(opener, (transform, aabb)));
+        if open && closed {
+            commands.entity(entity).remove::<BlocksMovement>();
+            commands.entity(entity).remove::<BlocksVision>();
+            *visibility = Visibility::Hidden;
+        } else if !open && !closed {
             commands.entity(entity).insert(BlocksMovement);
             commands.entity(entity).insert(BlocksVision);
             *visibility = Visibility::Inherited;
@@ -2,24 +2,31 @@ use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy::utils::HashMap;
-use bevy_ecs_ldtk::ldtk::FieldValue;
+use bevy_ecs_ldtk::ldtk::ldtk_fields::{LdtkFields, LdtkFieldsError};
 use bevy_ecs_ldtk::prelude::LdtkEntityAppExt;
 use bevy_ecs_ldtk::{EntityInstance, GridCoords, LdtkEntity, LdtkSpriteSheetBundle};
+use rand::Rng;
 
+use crate::game::ai::pathfinding::NavigationPath;
 use crate::game::ai::patrol::{PatrolBundle, PatrolMode, PatrolRoute, PatrolState, PatrolWaypoint};
-use crate::game::ai::AiState::{Chasing, ReturnedToPost};
+use crate::game::ai::search::{SearchSettings, SearchState};
+use crate::game::ai::suspicion::{Suspicion, SEARCHING_FILL_MULTIPLIER};
+use crate::game::ai::AiState::{Chasing, ReturnedToPost, Searching};
 use crate::game::ai::{AiState, HasAiState, Hunter};
 use crate::game::animation::{PlayerAnimation, PlayerAnimationState};
 use crate::game::assets::{ImageAsset, ImageAssets};
 use crate::game::audio::sfx::Sfx;
 use crate::game::dialog::{DialogLineType, ShowDialogEvent, ShowDialogType};
+use crate::game::end_game::PlayerDetected;
 use crate::game::grid::GridPosition;
 use crate::game::line_of_sight::vision::{
     Facing, VisibleSquares, VisionAbility, VisionArchetype, VisionBundle,
 };
 use crate::game::line_of_sight::vision_cones::RenderedFieldOfView;
 use crate::game::movement::GridMovement;
-use crate::game::spawn::health::{CanApplyDamage, OnDeath};
+use crate::game::rng::{GameRng, RngStream};
+use crate::game::spawn::health::CanApplyDamage;
+use crate::game::spawn::level::{cache_wall_locations, LevelWalls};
 use crate::game::spawn::player::Player;
 use crate::game::threat::{ThreatTimer, ThreatTimerSettings};
 use crate::screen::Screen;
@@ -30,8 +37,9 @@ pub(super) fn plugin(app: &mut App) {
     app.register_ldtk_entity::<LdtkEnemyBundle>("Enemy");
 
     // systems
+    app.add_systems(Update, fix_loaded_ldtk_entities.after(cache_wall_locations));
     app.add_systems(
-        Update,
+        FixedUpdate,
         (detect_player, return_to_post, follow_player)
             .chain()
             .run_if(in_state(Screen::Playing))
@@ -42,7 +50,7 @@ pub(super) fn plugin(app: &mut App) {
     app.register_type::<Enemy>();
     app.register_type::<CanSeePlayer>();
     app.register_type::<SpawnCoords>();
-    app.observe(on_death_reset_enemies);
+    app.register_type::<EnemySpeed>();
     app.observe(attach_anim_component);
 }
 
@@ -58,6 +66,23 @@ pub struct CanSeePlayer;
 #[reflect(Component)]
 pub struct SpawnCoords(pub GridPosition);
 
+/// How hard an enemy pushes itself around, depending on what it's doing
+#[derive(Component, Reflect, Debug, Copy, Clone)]
+#[reflect(Component)]
+pub struct EnemySpeed {
+    pub patrol: f32,
+    pub chase: f32,
+}
+
+impl Default for EnemySpeed {
+    fn default() -> Self {
+        Self {
+            patrol: ENEMY_PATROL_SPEED,
+            chase: ENEMY_CHASE_SPEED,
+        }
+    }
+}
+
 #[derive(Component, Default, Copy, Clone)]
 pub struct LdtkEnemy;
 
@@ -70,13 +95,28 @@ pub struct LdtkEnemyBundle {
     sprite_bundle: LdtkSpriteSheetBundle,
     #[grid_coords]
     grid_coords: GridCoords,
-    #[with(fix_loaded_ldtk_entities)]
-    enemy_bundle: EnemyBundle,
+    #[from_entity_instance]
+    entity_instance: EntityInstance,
 }
 
 /// Takes all ldtk enemy entities, and adds all the components we need for them to work in our game.
-fn fix_loaded_ldtk_entities(instance: &EntityInstance) -> EnemyBundle {
-    EnemyBundle::new(instance)
+/// This has to wait for the level walls to be cached, since LDtk's coordinates depend on the size of the level.
+fn fix_loaded_ldtk_entities(
+    query: Query<(Entity, &EntityInstance), With<LdtkEnemy>>,
+    walls: Res<LevelWalls>,
+    mut rng: ResMut<GameRng>,
+    mut commands: Commands,
+) {
+    for (ldtk_entity, instance) in query.iter() {
+        commands
+            .entity(ldtk_entity)
+            .remove::<LdtkEnemy>() // we have to remove it because it's used as the query for this function
+            .insert(EnemyBundle::new(
+                instance,
+                &walls,
+                rng.stream(RngStream::Ai),
+            ));
+    }
 }
 
 // This is what our game needs to make an enemy work, separate from LDTK
@@ -87,63 +127,74 @@ struct EnemyBundle {
     spawn_coords: SpawnCoords,
     grid_position: GridPosition,
     grid_movement: GridMovement,
+    speed: EnemySpeed,
     can_damage: CanApplyDamage,
     marker: Enemy,
     vision: VisionBundle,
     rendered_field_of_view: RenderedFieldOfView,
+    archetype: VisionArchetype,
     role: Hunter,
     ai_state: HasAiState,
     patrol_bundle: PatrolBundle,
+    navigation_path: NavigationPath,
+    search_state: SearchState,
+    suspicion: Suspicion,
 }
 
 impl EnemyBundle {
-    pub fn new(instance: &EntityInstance) -> Self {
-        const DEFAULT_WAYPOINT_WAIT_TIME: Duration = Duration::new(5, 0);
-        // todo delete this it's for testing - randomize types of enemies
-        //let mut rng = rand::thread_rng();
-        let is_sniper = false; //rng.gen_ratio(1, 3);
-        let vision_archetype = if is_sniper {
-            VisionArchetype::Sniper
-        } else {
-            VisionArchetype::Patrol
+    pub fn new(instance: &EntityInstance, walls: &LevelWalls, rng: &mut impl Rng) -> Self {
+        let vision_archetype = read_enum_field(
+            instance,
+            "Archetype",
+            VisionArchetype::Patrol,
+            |value| match value {
+                "Patrol" => Some(VisionArchetype::Patrol),
+                "Sniper" => Some(VisionArchetype::Sniper),
+                "Random" if rng.gen_ratio(1, 3) => Some(VisionArchetype::Sniper),
+                "Random" => Some(VisionArchetype::Patrol),
+                _ => None,
+            },
+        );
+        let patrol_mode =
+            read_enum_field(
+                instance,
+                "PatrolMode",
+                PatrolMode::Cycle,
+                |value| match value {
+                    "Cycle" => Some(PatrolMode::Cycle),
+                    "PingPong" => Some(PatrolMode::PingPong),
+                    _ => None,
+                },
+            );
+        let initial_facing = read_enum_field(
+            instance,
+            "InitialFacing",
+            Facing::default(),
+            |value| match value {
+                "Right" => Some(Facing(Vec2::new(1., 0.))),
+                "Up" => Some(Facing(Vec2::new(0., 1.))),
+                "Left" => Some(Facing(Vec2::new(-1., 0.))),
+                "Down" => Some(Facing(Vec2::new(0., -1.))),
+                _ => None,
+            },
+        );
+        let speed = EnemySpeed {
+            patrol: read_speed_field(instance, "PatrolSpeed", ENEMY_PATROL_SPEED),
+            chase: read_speed_field(instance, "ChaseSpeed", ENEMY_CHASE_SPEED),
         };
 
-        let grid_position =
-            GridPosition::new(instance.grid.x as f32, 64.0 - instance.grid.y as f32 - 1.0);
+        let grid_position = walls.ldtk_to_grid(instance.grid);
 
-        let mut ai = AiState::Idle;
+        let patrol_nodes = read_patrol_waypoints(instance, walls);
+        let ai = if patrol_nodes.is_empty() {
+            AiState::Idle
+        } else {
+            AiState::Patrolling
+        };
+        let first_wait_time = patrol_nodes
+            .first()
+            .map_or(DEFAULT_WAYPOINT_WAIT_TIME, |waypoint| waypoint.wait_time);
 
-        let mut patrol_nodes: Vec<PatrolWaypoint> = vec![];
-        for field in instance.field_instances.clone() {
-            if let FieldValue::Points(points) = field.value {
-                if points.is_empty() {
-                    ai = AiState::Idle;
-                    break;
-                }
-                for (i, point) in points.iter().enumerate() {
-                    let p = point.unwrap();
-                    let next_waypoint = if i == points.len() - 1 {
-                        points[0]
-                    } else {
-                        points[i + 1]
-                    };
-                    let facing = match next_waypoint {
-                        None => Facing::default(),
-                        Some(next_point) => {
-                            let direction = IVec2::new(next_point.x - p.x, p.y - next_point.y);
-                            Facing(direction.as_vec2())
-                        }
-                    };
-                    patrol_nodes.push(PatrolWaypoint {
-                        position: GridPosition::new(p.x as f32, 64.0 - p.y as f32 - 1.),
-                        facing,
-                        wait_time: DEFAULT_WAYPOINT_WAIT_TIME,
-                    });
-
-                    ai = AiState::Patrolling;
-                }
-            }
-        }
         Self {
             name: Name::new("LdtkEnemy"),
             marker: Enemy,
@@ -151,11 +202,14 @@ impl EnemyBundle {
             spawn_coords: SpawnCoords(grid_position),
             grid_position,
             grid_movement: GridMovement::default(),
+            speed,
             vision: VisionBundle {
                 vision_ability: VisionAbility::of(vision_archetype),
+                facing: initial_facing,
                 ..default()
             },
             rendered_field_of_view: RenderedFieldOfView,
+            archetype: vision_archetype,
             role: Hunter,
             ai_state: HasAiState {
                 current_state: ai,
@@ -166,18 +220,137 @@ impl EnemyBundle {
             patrol_bundle: PatrolBundle {
                 state: PatrolState {
                     current_waypoint: 0,
-                    wait_timer: Timer::new(DEFAULT_WAYPOINT_WAIT_TIME, TimerMode::Once),
+                    wait_timer: Timer::new(first_wait_time, TimerMode::Once),
                     direction: 1,
                 },
                 route: PatrolRoute {
                     waypoints: patrol_nodes,
-                    mode: PatrolMode::Cycle,
+                    mode: patrol_mode,
                 },
             },
+            navigation_path: NavigationPath::default(),
+            search_state: SearchState::default(),
+            suspicion: Suspicion::of(vision_archetype),
+        }
+    }
+}
+
+const DEFAULT_WAYPOINT_WAIT_TIME: Duration = Duration::new(5, 0);
+
+/// Reads an LDtk enum field, falling back to `default` (with a warning) if it holds a value we don't know about.
+/// Enemies placed before a field existed won't have it at all, which is fine and doesn't warn.
+fn read_enum_field<T: std::fmt::Debug>(
+    instance: &EntityInstance,
+    identifier: &str,
+    default: T,
+    mut parse: impl FnMut(&str) -> Option<T>,
+) -> T {
+    match instance.get_maybe_enum_field(identifier) {
+        Ok(Some(value)) => parse(value).unwrap_or_else(|| {
+            warn!(
+                "Enemy {} has unknown {} value {:?}, using {:?} instead",
+                instance.iid, identifier, value, default
+            );
+            default
+        }),
+        Ok(None) | Err(LdtkFieldsError::FieldNotFound { .. }) => default,
+        Err(error) => {
+            warn!(
+                "Enemy {} has a broken field: {}, using {:?} instead",
+                instance.iid, error, default
+            );
+            default
+        }
+    }
+}
+
+/// Reads an LDtk float field holding a movement speed, which has to be a sensible non-negative number
+fn read_speed_field(instance: &EntityInstance, identifier: &str, default: f32) -> f32 {
+    match instance.get_maybe_float_field(identifier) {
+        Ok(Some(speed)) if speed.is_finite() && *speed >= 0.0 => *speed,
+        Ok(Some(speed)) => {
+            warn!(
+                "Enemy {} has invalid {} {}, it must be zero or more. Using {} instead",
+                instance.iid, identifier, speed, default
+            );
+            default
+        }
+        Ok(None) | Err(LdtkFieldsError::FieldNotFound { .. }) => default,
+        Err(error) => {
+            warn!(
+                "Enemy {} has a broken field: {}, using {} instead",
+                instance.iid, error, default
+            );
+            default
         }
     }
 }
 
+/// Builds the patrol route from the `PatrolPath` points, waiting at each one for the matching `WaitSeconds` entry.
+fn read_patrol_waypoints(instance: &EntityInstance, walls: &LevelWalls) -> Vec<PatrolWaypoint> {
+    let points: Vec<GridPosition> = match instance.get_maybe_points_field("PatrolPath") {
+        Ok(points) => points
+            .iter()
+            .flatten()
+            .map(|&point| walls.ldtk_to_grid(point))
+            .collect(),
+        Err(LdtkFieldsError::FieldNotFound { .. }) => vec![],
+        Err(error) => {
+            warn!(
+                "Enemy {} has a broken field: {}, it won't patrol",
+                instance.iid, error
+            );
+            vec![]
+        }
+    };
+
+    let wait_seconds: &[Option<f32>] = match instance.get_maybe_floats_field("WaitSeconds") {
+        Ok(wait_seconds) => wait_seconds,
+        Err(LdtkFieldsError::FieldNotFound { .. }) => &[],
+        Err(error) => {
+            warn!(
+                "Enemy {} has a broken field: {}, waiting {:?} at every patrol point",
+                instance.iid, error, DEFAULT_WAYPOINT_WAIT_TIME
+            );
+            &[]
+        }
+    };
+    if wait_seconds.len() > points.len() {
+        warn!(
+            "Enemy {} has {} WaitSeconds but only {} patrol points, ignoring the extra wait times",
+            instance.iid,
+            wait_seconds.len(),
+            points.len()
+        );
+    }
+
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &position)| {
+            let next_position = points[(i + 1) % points.len()];
+            let wait_time = match wait_seconds.get(i).copied().flatten() {
+                None => DEFAULT_WAYPOINT_WAIT_TIME,
+                Some(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                    Duration::from_secs_f32(seconds)
+                }
+                Some(seconds) => {
+                    warn!(
+                        "Enemy {} has invalid WaitSeconds {} for patrol point {}, using {:?} instead",
+                        instance.iid, seconds, i, DEFAULT_WAYPOINT_WAIT_TIME
+                    );
+                    DEFAULT_WAYPOINT_WAIT_TIME
+                }
+            };
+            PatrolWaypoint {
+                position,
+                facing: Facing(position.direction_to(&next_position)),
+                wait_time,
+            }
+        })
+        .collect()
+}
+
 #[derive(Event, Debug)]
 pub struct SpawnEnemyTrigger;
 
@@ -207,25 +380,42 @@ fn rotate_facing(
 }
 
 fn detect_player(
-    aware_enemies: Query<(Entity, &Transform, &VisibleSquares), (With<Enemy>, With<CanSeePlayer>)>,
-    unaware_enemies: Query<(Entity, &VisibleSquares), (With<Enemy>, Without<CanSeePlayer>)>,
-    player: Query<(&GridPosition, &Transform), With<Player>>,
+    mut aware_enemies: Query<
+        (Entity, &Transform, &VisibleSquares, &mut SearchState),
+        (With<Enemy>, With<CanSeePlayer>),
+    >,
+    mut unaware_enemies: Query<
+        (
+            Entity,
+            &GridPosition,
+            &Facing,
+            &VisionAbility,
+            &VisibleSquares,
+            &HasAiState,
+            &mut Suspicion,
+        ),
+        (With<Enemy>, Without<CanSeePlayer>),
+    >,
+    player: Query<(&GridPosition, &Transform, &GridMovement), With<Player>>,
     threat_timer: Res<ThreatTimer>,
     threat_settings: Res<ThreatTimerSettings>,
+    search_settings: Res<SearchSettings>,
+    time: Res<Time>,
     mut commands: Commands,
 ) {
-    let Ok((player_grid_pos, player_transform)) = player.get_single() else {
+    let Ok((player_grid_pos, player_transform, player_movement)) = player.get_single() else {
         return;
     };
 
     if threat_timer.current_level >= threat_settings.levels - 1 {
-        for (enemy_entity, _) in &unaware_enemies {
+        for (enemy_entity, .., mut suspicion) in &mut unaware_enemies {
+            suspicion.notice(*player_grid_pos, 1.0, 1.0);
             commands.entity(enemy_entity).insert(CanSeePlayer);
         }
         return;
     }
 
-    for (enemy_entity, enemy_transform, enemy_vision) in &aware_enemies {
+    for (enemy_entity, enemy_transform, enemy_vision, mut search) in &mut aware_enemies {
         if !enemy_vision.contains(player_grid_pos)
             && enemy_transform
                 .translation
@@ -233,6 +423,7 @@ fn detect_player(
                 > ENEMY_CHASE_RANGE
         {
             commands.entity(enemy_entity).remove::<CanSeePlayer>();
+            search.begin(*player_grid_pos, &search_settings);
             commands.trigger(Sfx::LostPlayer);
             commands.trigger(ShowDialogEvent {
                 entity: enemy_entity,
@@ -240,10 +431,29 @@ fn detect_player(
             });
         }
     }
-    for (enemy_entity, enemy_vision) in &unaware_enemies {
-        if enemy_vision.contains(player_grid_pos) {
+    // everyone gets jumpier as the threat level rises
+    let threat_multiplier = 1.0 + threat_timer.current_level as f32 / threat_settings.levels as f32;
+    let dt = time.delta_seconds();
+    for (enemy_entity, enemy_pos, facing, vision, enemy_vision, ai, mut suspicion) in
+        &mut unaware_enemies
+    {
+        if !enemy_vision.contains(player_grid_pos) {
+            suspicion.decay(dt);
+            continue;
+        }
+
+        let mut fill_rate =
+            suspicion.fill_rate(enemy_pos, facing, vision, player_grid_pos, player_movement)
+                * threat_multiplier;
+        if ai.current_state == Searching {
+            fill_rate *= SEARCHING_FILL_MULTIPLIER;
+        }
+        suspicion.notice(*player_grid_pos, fill_rate, dt);
+
+        if suspicion.is_full() {
             commands.entity(enemy_entity).insert(CanSeePlayer);
             commands.trigger(Sfx::Detected);
+            commands.trigger(PlayerDetected);
             commands.trigger(ShowDialogEvent {
                 entity: enemy_entity,
                 dialog_type: ShowDialogType::RandomLine(DialogLineType::EnemySpotsPlayer),
@@ -255,6 +465,7 @@ fn detect_player(
 pub const ENEMY_CHASE_SPEED: f32 = 0.5;
 pub const ENEMY_PATROL_SPEED: f32 = 0.3;
 pub const ENEMY_RETURN_TO_POST_SPEED: f32 = 0.3;
+pub const ENEMY_SEARCH_SPEED: f32 = 0.4;
 pub const ENEMY_CHASE_RANGE: f32 = 100.0;
 
 fn return_to_post(
@@ -264,19 +475,22 @@ fn return_to_post(
             &GridPosition,
             &SpawnCoords,
             &mut HasAiState,
+            &mut NavigationPath,
         ),
         (With<Enemy>, Without<CanSeePlayer>),
     >,
+    walls: Res<LevelWalls>,
 ) {
-    for (mut movement, &position, spawn, mut ai) in &mut unaware_enemies {
+    for (mut movement, &position, spawn, mut ai, mut path) in &mut unaware_enemies {
         if ai.current_state == AiState::ReturningToPost {
-            let direction = position.direction_to(&spawn.0);
-            if direction.length() < 1.0 {
+            if position.direction_to(&spawn.0).length() < 1.0 {
                 movement.acceleration_player_force = Vec2::ZERO;
                 ai.current_state = ReturnedToPost;
+                path.invalidate();
             } else {
+                let direction = path.steer(&walls, &position, &spawn.0);
                 movement.acceleration_player_force =
-                    direction.normalize() * ENEMY_RETURN_TO_POST_SPEED;
+                    direction.normalize_or_zero() * ENEMY_RETURN_TO_POST_SPEED;
             }
         }
     }
@@ -284,44 +498,32 @@ fn return_to_post(
 
 pub(crate) fn follow_player(
     mut enemy_movement_controllers: Query<
-        (&mut GridMovement, &mut Facing, &GridPosition, &HasAiState),
+        (
+            &mut GridMovement,
+            &mut Facing,
+            &GridPosition,
+            &HasAiState,
+            &EnemySpeed,
+            &mut NavigationPath,
+        ),
         (With<Enemy>, With<CanSeePlayer>),
     >,
     player: Query<&GridPosition, With<Player>>,
+    walls: Res<LevelWalls>,
 ) {
     let Ok(player_pos) = player.get_single() else {
         return;
     };
 
-    for (mut controller, mut facing, enemy_pos, ai) in &mut enemy_movement_controllers {
+    for (mut controller, mut facing, enemy_pos, ai, speed, mut path) in
+        &mut enemy_movement_controllers
+    {
         if ai.current_state == Chasing {
-            let direction = enemy_pos.direction_to(player_pos);
-            facing.0 = direction;
-            controller.acceleration_player_force = direction.normalize() * ENEMY_CHASE_SPEED;
-        }
-    }
-}
-
-fn on_death_reset_enemies(
-    _trigger: Trigger<OnDeath>,
-    mut query: Query<
-        (
-            Entity,
-            &mut GridPosition,
-            &SpawnCoords,
-            &mut Facing,
-            Option<&mut PatrolState>,
-        ),
-        With<Enemy>,
-    >,
-    mut commands: Commands,
-) {
-    for (enemy, mut pos, spawn_point, mut facing, maybe_patrol) in &mut query {
-        *pos = spawn_point.0;
-        facing.0 = Vec2::new(1., 0.);
-        commands.entity(enemy).remove::<CanSeePlayer>();
-        if let Some(mut patrol) = maybe_patrol {
-            patrol.current_waypoint = 0;
+            let direction = path.steer(&walls, enemy_pos, player_pos);
+            if direction != Vec2::ZERO {
+                facing.0 = direction;
+            }
+            controller.acceleration_player_force = direction.normalize_or_zero() * speed.chase;
         }
     }
 }
@@ -366,3 +568,176 @@ fn attach_anim_component(
         enemy_animation,
     ));
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::ldtk::{FieldInstance, FieldValue};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn enemy_with_fields(fields: Vec<(&str, FieldValue)>) -> EntityInstance {
+        EntityInstance {
+            iid: "test-enemy".to_string(),
+            field_instances: fields
+                .into_iter()
+                .map(|(identifier, value)| FieldInstance {
+                    identifier: identifier.to_string(),
+                    value,
+                    field_instance_type: String::new(),
+                    tile: None,
+                    def_uid: 0,
+                    real_editor_values: vec![],
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    fn walls(level_height: i32) -> LevelWalls {
+        LevelWalls {
+            level_width: 64,
+            level_height,
+            ..default()
+        }
+    }
+
+    fn enum_value(value: &str) -> FieldValue {
+        FieldValue::Enum(Some(value.to_string()))
+    }
+
+    fn rng() -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(0)
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let bundle = EnemyBundle::new(&enemy_with_fields(vec![]), &walls(64), &mut rng());
+
+        assert!(bundle.ai_state.current_state == AiState::Idle);
+        assert_eq!(bundle.patrol_bundle.route.mode, PatrolMode::Cycle);
+        assert_eq!(bundle.speed.patrol, ENEMY_PATROL_SPEED);
+        assert_eq!(bundle.speed.chase, ENEMY_CHASE_SPEED);
+        assert_eq!(bundle.vision.facing.0, Vec2::new(1., 0.));
+    }
+
+    #[test]
+    fn reads_configured_fields() {
+        let bundle = EnemyBundle::new(
+            &enemy_with_fields(vec![
+                ("Archetype", enum_value("Sniper")),
+                ("PatrolMode", enum_value("PingPong")),
+                ("PatrolSpeed", FieldValue::Float(Some(0.1))),
+                ("ChaseSpeed", FieldValue::Float(Some(0.9))),
+                ("InitialFacing", enum_value("Down")),
+            ]),
+            &walls(64),
+            &mut rng(),
+        );
+
+        assert_eq!(
+            bundle.suspicion.seconds_to_detect,
+            Suspicion::of(VisionArchetype::Sniper).seconds_to_detect
+        );
+        assert_eq!(bundle.patrol_bundle.route.mode, PatrolMode::PingPong);
+        assert_eq!(bundle.speed.patrol, 0.1);
+        assert_eq!(bundle.speed.chase, 0.9);
+        assert_eq!(bundle.vision.facing.0, Vec2::new(0., -1.));
+    }
+
+    #[test_case("Archetype", enum_value("Juggernaut") ; "unknown enum value")]
+    #[test_case("Archetype", FieldValue::Float(Some(1.0)) ; "wrong field type")]
+    #[test_case("PatrolSpeed", FieldValue::Float(Some(-1.0)) ; "negative speed")]
+    #[test_case("ChaseSpeed", FieldValue::Float(Some(f32::NAN)) ; "nan speed")]
+    fn invalid_values_fall_back_to_defaults(identifier: &str, value: FieldValue) {
+        let bundle = EnemyBundle::new(
+            &enemy_with_fields(vec![(identifier, value)]),
+            &walls(64),
+            &mut rng(),
+        );
+
+        assert_eq!(
+            bundle.suspicion.seconds_to_detect,
+            Suspicion::of(VisionArchetype::Patrol).seconds_to_detect
+        );
+        assert_eq!(bundle.speed.patrol, ENEMY_PATROL_SPEED);
+        assert_eq!(bundle.speed.chase, ENEMY_CHASE_SPEED);
+    }
+
+    #[test]
+    fn random_archetypes_are_drawn_from_the_seed() {
+        let archetypes = |seed: u64| -> Vec<f32> {
+            let mut rng = GameRng::new(seed);
+            (0..12)
+                .map(|_| {
+                    EnemyBundle::new(
+                        &enemy_with_fields(vec![("Archetype", enum_value("Random"))]),
+                        &walls(64),
+                        rng.stream(RngStream::Ai),
+                    )
+                    .suspicion
+                    .seconds_to_detect
+                })
+                .collect()
+        };
+
+        assert_eq!(archetypes(3), archetypes(3));
+        for archetype in [VisionArchetype::Patrol, VisionArchetype::Sniper] {
+            assert!(archetypes(3).contains(&Suspicion::of(archetype).seconds_to_detect));
+        }
+    }
+
+    #[test]
+    fn waypoints_wait_for_their_own_time() {
+        let bundle = EnemyBundle::new(
+            &enemy_with_fields(vec![
+                (
+                    "PatrolPath",
+                    FieldValue::Points(vec![
+                        Some(IVec2::new(1, 1)),
+                        Some(IVec2::new(3, 1)),
+                        Some(IVec2::new(3, 3)),
+                    ]),
+                ),
+                (
+                    "WaitSeconds",
+                    FieldValue::Floats(vec![Some(1.5), Some(-2.0)]),
+                ),
+            ]),
+            &walls(64),
+            &mut rng(),
+        );
+
+        let waypoints = &bundle.patrol_bundle.route.waypoints;
+        assert!(bundle.ai_state.current_state == AiState::Patrolling);
+        assert_eq!(waypoints.len(), 3);
+        assert_eq!(waypoints[0].wait_time, Duration::from_secs_f32(1.5));
+        assert_eq!(waypoints[1].wait_time, DEFAULT_WAYPOINT_WAIT_TIME);
+        assert_eq!(waypoints[2].wait_time, DEFAULT_WAYPOINT_WAIT_TIME);
+        assert_eq!(
+            bundle.patrol_bundle.state.wait_timer.duration(),
+            Duration::from_secs_f32(1.5)
+        );
+        // facing the next waypoint, with LDtk's y axis pointing down
+        assert_eq!(waypoints[1].facing.0, Vec2::new(0., -2.));
+    }
+
+    #[test]
+    fn ldtk_coordinates_are_flipped_using_the_level_height() {
+        let mut instance = enemy_with_fields(vec![(
+            "PatrolPath",
+            FieldValue::Points(vec![Some(IVec2::new(2, 1)), Some(IVec2::new(2, 7))]),
+        )]);
+        instance.grid = IVec2::new(2, 1);
+
+        let bundle = EnemyBundle::new(&instance, &walls(10), &mut rng());
+
+        assert_eq!(bundle.grid_position, GridPosition::new(2., 8.));
+        assert_eq!(
+            bundle.patrol_bundle.route.waypoints[1].position,
+            GridPosition::new(2., 2.)
+        );
+    }
+}
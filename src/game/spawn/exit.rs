@@ -1,16 +1,17 @@
 use bevy::{prelude::*, render::primitives::Aabb};
 use bevy_ecs_ldtk::prelude::LdtkEntityAppExt;
-use bevy_ecs_ldtk::{EntityInstance, GridCoords, LdtkEntity, LdtkSpriteSheetBundle};
+use bevy_ecs_ldtk::{GridCoords, LdtkEntity, LdtkSpriteSheetBundle};
 
 use super::player::Player;
 use crate::game::dialog::{DialogLineType, ShowDialogEvent, ShowDialogType};
 use crate::game::line_of_sight::BlocksVision;
-use crate::game::spawn::keys::{CanPickup, Key};
 use crate::game::spawn::level::BlocksMovement;
 use crate::game::{grid::GridPosition, utilities::intersect};
+use crate::AppSet;
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(Update, open_locked_doors);
+    app.add_systems(Update, fix_loaded_ldtk_entities);
+    app.add_systems(FixedUpdate, open_locked_doors.in_set(AppSet::Collide));
     app.register_ldtk_entity::<LdtkLockedDoorBundle>("DoorLocked");
     app.init_resource::<NumKeysPickedUp>();
 
@@ -27,22 +28,28 @@ struct Exit;
 pub struct CanBeUnlocked;
 
 #[derive(Component, Default, Copy, Clone)]
-pub struct LockedDoor;
+pub struct LdtkLockedDoor;
 
 #[derive(Default, Bundle, LdtkEntity)]
 struct LdtkLockedDoorBundle {
-    tag: crate::game::spawn::bars::LdtkBars,
+    tag: LdtkLockedDoor,
     #[sprite_sheet_bundle]
     sprite_bundle: LdtkSpriteSheetBundle,
     #[grid_coords]
     grid_coords: GridCoords,
-    #[with(fix_loaded_ldtk_entities)]
-    locked_door_bundle: LockedDoorBundle,
 }
 
-/// Takes all ldtk enemy entities, and adds all the components we need for them to work in our game.
-fn fix_loaded_ldtk_entities(instance: &EntityInstance) -> LockedDoorBundle {
-    LockedDoorBundle::new(instance)
+/// Takes all ldtk locked door entities, and adds all the components we need for them to work in our game.
+fn fix_loaded_ldtk_entities(
+    query: Query<(Entity, &GridCoords), With<LdtkLockedDoor>>,
+    mut commands: Commands,
+) {
+    for (ldtk_entity, grid_coords) in query.iter() {
+        commands
+            .entity(ldtk_entity)
+            .remove::<LdtkLockedDoor>() // we have to remove it because it's used as the query for this function
+            .insert(LockedDoorBundle::new(grid_coords));
+    }
 }
 
 // This is what our game needs to make an enemy work, separate from LDTK
@@ -58,9 +65,8 @@ struct LockedDoorBundle {
 }
 
 impl LockedDoorBundle {
-    pub fn new(instance: &EntityInstance) -> Self {
-        let grid_position =
-            GridPosition::new(instance.grid.x as f32, 64.0 - instance.grid.y as f32 - 1.0);
+    pub fn new(grid_coords: &GridCoords) -> Self {
+        let grid_position = GridPosition::new(grid_coords.x as f32, grid_coords.y as f32);
 
         Self {
             name: Name::new("Exit"),
@@ -88,7 +94,6 @@ fn open_locked_doors(
             With<BlocksVision>,
         ),
     >,
-    key: Query<Entity, (With<Key>, Without<CanPickup>)>,
     mut commands: Commands,
 ) {
     let Ok((player_ent, player_transform, player_aabb)) = player_query.get_single() else {
@@ -107,12 +112,6 @@ fn open_locked_doors(
                     entity: player_ent,
                     dialog_type: ShowDialogType::NextLine(DialogLineType::PlayerUnlocksDoor),
                 });
-
-                let keys: Vec<_> = key.iter().collect();
-                if let Some(key) = keys.first() {
-                    info!("Despawning key {:?}", &key);
-                    commands.entity(*key).despawn();
-                }
             }
         }
     }
@@ -4,11 +4,12 @@ use bevy::{prelude::*, render::primitives::Aabb};
 use bevy_ecs_ldtk::prelude::*;
 
 use crate::game::{
-    end_game::EndGameCondition,
+    campaign::{Campaign, LevelCompleted},
     grid::GridPosition,
     line_of_sight::{vision::VisionAbility, CanRevealFog, PlayerLineOfSightBundle},
     utilities::intersect,
 };
+use crate::AppSet;
 
 use super::{enemy::SpawnCoords, player::Player};
 
@@ -16,11 +17,11 @@ pub fn plugin(app: &mut App) {
     app.register_ldtk_entity::<LdtkGoalBundle>("Goal");
     // systems
     app.add_systems(Update, fix_loaded_ldtk_entities);
-    app.add_systems(Update, check_exit);
+    app.add_systems(FixedUpdate, check_exit.in_set(AppSet::Collide));
 }
 
 #[derive(Component, Default, Copy, Clone)]
-struct LdtkGoal;
+pub struct LdtkGoal;
 
 #[derive(Default, Bundle, LdtkEntity)]
 struct LdtkGoalBundle {
@@ -53,7 +54,6 @@ impl GoalBundle {
                     field_of_view_radians: 2.0 * consts::PI,
                     range_in_grid_units: 1.0,
                 },
-                facing_walls_cache: Default::default(),
                 visible_squares: Default::default(),
             },
         }
@@ -79,8 +79,12 @@ fn fix_loaded_ldtk_entities(
 fn check_exit(
     player_query: Query<(&Transform, &Aabb), With<Player>>,
     exit_query: Query<(&Transform, &Aabb), With<Goal>>,
+    campaign: Res<Campaign>,
     mut commands: Commands,
 ) {
+    if campaign.is_changing_level() {
+        return;
+    }
     let Ok(player) = player_query.get_single() else {
         return;
     };
@@ -88,6 +92,6 @@ fn check_exit(
         return;
     };
     if intersect(player, exit) {
-        commands.trigger(EndGameCondition::Win);
+        commands.trigger(LevelCompleted);
     }
 }
@@ -15,8 +15,8 @@ pub(super) fn plugin(app: &mut App) {
     app.register_type::<CanApplyDamage>();
     app.register_type::<Health>();
     app.add_systems(
-        Update,
-        apply_damage_on_collision.in_set(AppSet::UpdateWorld),
+        FixedUpdate,
+        apply_damage_on_collision.in_set(AppSet::Collide),
     );
     app.observe(on_receive_damage);
 }
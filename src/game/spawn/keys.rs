@@ -2,13 +2,13 @@ use std::f32::consts;
 
 use bevy::app::{App, Update};
 use bevy::core::Name;
-use bevy::math::Vec3;
 use bevy::prelude::*;
 use bevy::render::primitives::Aabb;
 use bevy_ecs_ldtk::prelude::LdtkEntityAppExt;
 use bevy_ecs_ldtk::{GridCoords, LdtkEntity, LdtkSpriteSheetBundle};
 
 use crate::game::audio::sfx::Sfx;
+use crate::game::campaign::LevelStarted;
 use crate::game::dialog::{DialogLineType, ShowDialogEvent, ShowDialogType};
 use crate::game::end_game::EndGameCondition;
 use crate::game::grid::GridPosition;
@@ -18,6 +18,7 @@ use crate::game::spawn::enemy::SpawnCoords;
 use crate::game::spawn::exit::NumKeysPickedUp;
 use crate::game::spawn::health::OnDeath;
 use crate::game::utilities::intersect;
+use crate::AppSet;
 
 use super::player::Player;
 
@@ -27,17 +28,21 @@ pub(super) fn plugin(app: &mut App) {
 
     // systems
     app.add_systems(Update, fix_loaded_ldtk_entities);
-    app.add_systems(Update, pickup_key);
+    app.add_systems(FixedUpdate, pickup_key.in_set(AppSet::Collide));
     // reflection
     app.register_type::<Key>();
+    app.register_type::<CanPickup>();
     app.observe(on_end_game_reset_keys);
-    app.observe(on_death_respawn_key);
+    app.observe(on_level_start_forget_keys);
+    app.observe(on_death_drop_keys);
 }
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
 pub struct Key;
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct CanPickup;
 
 #[derive(Component, Default, Copy, Clone)]
@@ -73,7 +78,6 @@ impl KeyBundle {
                     field_of_view_radians: 2.0 * consts::PI,
                     range_in_grid_units: 1.0,
                 },
-                facing_walls_cache: Default::default(),
                 visible_squares: Default::default(),
             },
         }
@@ -106,6 +110,14 @@ fn on_end_game_reset_keys(
     }
 }
 
+/// Keys only open doors in the level they were found in
+fn on_level_start_forget_keys(
+    _trigger: Trigger<LevelStarted>,
+    mut picked_up_keys: ResMut<NumKeysPickedUp>,
+) {
+    picked_up_keys.0 = 0;
+}
+
 fn pickup_key(
     player: Query<(Entity, &Transform, &Aabb), (With<Player>, Without<Key>)>,
     mut keys: Query<(Entity, &mut Transform, &Aabb), (With<Key>, With<CanPickup>)>,
@@ -131,25 +143,13 @@ fn pickup_key(
     }
 }
 
-pub fn on_death_respawn_key(
+/// The snapshot puts picked up keys back where they were, this lets the player hear them drop
+fn on_death_drop_keys(
     _trigger: Trigger<OnDeath>,
-    mut keys: Query<
-        (Entity, &mut Transform, &mut GridPosition, &SpawnCoords),
-        (With<Key>, Without<CanPickup>),
-    >,
+    keys: Query<(), (With<Key>, Without<CanPickup>)>,
     mut commands: Commands,
-    mut num_keys_picked_up: ResMut<NumKeysPickedUp>,
 ) {
-    for (key_entity, mut transform, mut grid_pos, spawn_coords) in &mut keys {
-        let coordinates = spawn_coords.0.coordinates;
-        commands.entity(key_entity).insert(CanPickup);
-
-        grid_pos.coordinates = coordinates;
-        grid_pos.offset = Vec2::ZERO;
-
-        transform.translation.z = 20.;
-
+    for _ in &keys {
         commands.trigger(Sfx::KeyDrop);
-        num_keys_picked_up.0 = 0;
     }
 }
@@ -8,7 +8,6 @@ use bevy_ecs_ldtk::prelude::{LdtkIntCellAppExt, LevelMetadataAccessor};
 use bevy_ecs_ldtk::{GridCoords, LdtkIntCell, LevelEvent};
 
 use crate::game::grid::GridPosition;
-use crate::game::line_of_sight::front_facing_edges::RebuildCache;
 use crate::game::line_of_sight::BlocksVision;
 use crate::game::spawn::enemy::SpawnEnemyTrigger;
 
@@ -26,13 +25,15 @@ pub(super) fn plugin(app: &mut App) {
     app.observe(rebuild_movement_cache_on_remove);
     app.observe(rebuild_movement_cache_on_add);
     // reflection
+    app.register_type::<BlocksMovement>();
     app.register_type::<LevelWalls>();
     app.register_type::<LevelVisionBlockers>();
 }
 
 pub const GRID_SIZE: i32 = 16;
 
-#[derive(Default, Component, Copy, Clone)]
+#[derive(Default, Component, Copy, Clone, Reflect)]
+#[reflect(Component)]
 pub struct BlocksMovement;
 
 #[derive(Default, Bundle, LdtkIntCell)]
@@ -84,6 +85,14 @@ impl LevelWalls {
     pub fn collides_gridpos(&self, gridpos: &GridPosition) -> bool {
         self.collides(gridpos.coordinates.x as i32, gridpos.coordinates.y as i32)
     }
+
+    /// LDtk counts rows down from the top of the level, while we count them up from the bottom
+    pub fn ldtk_to_grid(&self, ldtk_coords: IVec2) -> GridPosition {
+        GridPosition::new(
+            ldtk_coords.x as f32,
+            (self.level_height - ldtk_coords.y - 1) as f32,
+        )
+    }
 }
 
 #[derive(Event, Debug)]
@@ -97,7 +106,7 @@ fn spawn_level(_trigger: Trigger<SpawnLevel>, mut commands: Commands) {
     commands.trigger(SpawnExitTrigger);
 }
 
-fn cache_wall_locations(
+pub(crate) fn cache_wall_locations(
     mut level_walls: ResMut<LevelWalls>,
     mut level_events: EventReader<LevelEvent>,
     walls_query: Query<&GridCoords, With<BlocksMovement>>,
@@ -130,7 +139,6 @@ fn cache_vision_blocker_locations(
     vision_blocker_query: Query<&GridCoords, With<BlocksVision>>,
     ldtk_project_entities: Query<&Handle<LdtkProject>>,
     ldtk_project_assets: Res<Assets<LdtkProject>>,
-    mut commands: Commands,
 ) {
     for level_event in level_events.read() {
         let LevelEvent::Spawned(level_iid) = level_event else {
@@ -149,12 +157,11 @@ fn cache_vision_blocker_locations(
             level_height: level.px_hei / GRID_SIZE,
         };
         *level_vision_blocker = new_vision_blocker;
-        commands.trigger(RebuildCache);
     }
 }
 
 fn rebuild_movement_cache_on_remove(
-    trigger: Trigger<OnRemove, BlocksVision>,
+    trigger: Trigger<OnRemove, BlocksMovement>,
     mut movement_blocker: ResMut<LevelWalls>,
     query: Query<(Entity, &GridCoords)>,
 ) {
@@ -165,7 +172,7 @@ fn rebuild_movement_cache_on_remove(
 }
 
 fn rebuild_movement_cache_on_add(
-    trigger: Trigger<OnAdd, BlocksVision>,
+    trigger: Trigger<OnAdd, BlocksMovement>,
     mut movement_blocker: ResMut<LevelWalls>,
     query: Query<(Entity, &GridCoords)>,
 ) {
@@ -5,17 +5,17 @@
 use bevy::prelude::*;
 
 mod bars;
-mod door;
+pub mod door;
 pub(crate) mod enemy;
-mod exit;
-mod goal;
+pub(crate) mod exit;
+pub(crate) mod goal;
 pub mod health;
 pub(crate) mod keys;
 mod ldtk;
 pub mod level;
 pub mod player;
 
-pub(super) fn plugin(app: &mut App) {
+pub(crate) fn plugin(app: &mut App) {
     app.add_plugins((
         health::plugin,
         level::plugin,
@@ -1,29 +1,34 @@
 //! Spawn the player.
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::LdtkEntityAppExt;
+use bevy_ecs_ldtk::{GridCoords, LdtkEntity};
 use leafwing_input_manager::InputManagerBundle;
 
+use crate::game::campaign::LevelStarted;
 use crate::game::dialog::{DialogLineType, ShowDialogEvent, ShowDialogType};
 use crate::game::grid::GridPosition;
 use crate::game::line_of_sight::PlayerLineOfSightBundle;
 use crate::game::movement::GridMovement;
 use crate::game::movement::RollState;
+use crate::game::spawn::door::CanOpenDoors;
 use crate::game::spawn::health::{CanReceiveDamage, SpawnPointGridPosition};
 use crate::input::PlayerAction;
+use crate::settings::Settings;
 use crate::{
     game::{
         animation::PlayerAnimation,
         assets::{ImageAsset, ImageAssets},
         camera::CameraFollowTarget,
-        spawn::ldtk::LdtkEntityBundle,
     },
     screen::Screen,
 };
 
 pub(super) fn plugin(app: &mut App) {
     app.observe(spawn_player);
+    app.observe(on_level_start_reset_player);
+    app.add_systems(Update, move_player_to_level_start);
     app.register_type::<Player>();
-    app.register_ldtk_entity::<LdtkEntityBundle>("Player");
+    app.register_ldtk_entity::<LdtkPlayerStartBundle>("Player");
 }
 
 #[derive(Event, Debug)]
@@ -33,10 +38,23 @@ pub struct SpawnPlayerTrigger;
 #[reflect(Component)]
 pub struct Player;
 
+/// Where the player starts the level, placed with the `Player` entity in LDtk
+#[derive(Component, Default, Copy, Clone)]
+pub struct PlayerStart;
+
+#[derive(Default, Bundle, LdtkEntity)]
+struct LdtkPlayerStartBundle {
+    tag: PlayerStart,
+    #[grid_coords]
+    grid_coords: GridCoords,
+}
+
 fn spawn_player(
     _trigger: Trigger<SpawnPlayerTrigger>,
     mut commands: Commands,
+    player_starts: Query<&GridCoords, With<PlayerStart>>,
     images: Res<ImageAssets>,
+    settings: Res<Settings>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
     // A texture atlas is a way to split one image with a grid into multiple sprites.
@@ -50,6 +68,11 @@ fn spawn_player(
     let mut player_transform = Transform::from_scale(Vec2::splat(1.).extend(1.0));
     player_transform.translation.z = 10.; // ensure player goes above level
 
+    // if the level hasn't spawned yet, we get moved to the start once it has
+    let start = player_starts.iter().next().map_or(Vec2::ZERO, |coords| {
+        Vec2::new(coords.x as f32, coords.y as f32)
+    });
+
     let entity_id = commands
         .spawn((
             Name::new("Player"),
@@ -65,12 +88,13 @@ fn spawn_player(
                 layout: texture_atlas_layout.clone(),
                 index: player_animation.get_atlas_index(),
             },
-            SpawnPointGridPosition(Vec2::new(29., 64. - 28.)),
+            SpawnPointGridPosition(start),
             CanReceiveDamage,
-            GridPosition::new(29., 64. - 28.),
+            CanOpenDoors,
+            GridPosition::new(start.x, start.y),
             GridMovement::default(),
             RollState::default(),
-            InputManagerBundle::with_map(PlayerAction::default_input_map()),
+            InputManagerBundle::with_map(PlayerAction::input_map_for(&settings.controls)),
             player_animation,
             PlayerLineOfSightBundle { ..default() },
         ))
@@ -81,3 +105,46 @@ fn spawn_player(
         dialog_type: ShowDialogType::NextLine(DialogLineType::PlayerSpawn),
     });
 }
+
+/// Put the player back at the start when moving on to the next level
+fn on_level_start_reset_player(
+    _trigger: Trigger<LevelStarted>,
+    mut query: Query<
+        (
+            &mut GridPosition,
+            &mut GridMovement,
+            &mut RollState,
+            &SpawnPointGridPosition,
+        ),
+        With<Player>,
+    >,
+) {
+    for (mut position, mut movement, mut roll_state, spawn_point) in &mut query {
+        *position = GridPosition::new(spawn_point.0.x, spawn_point.0.y);
+        movement.reset();
+        *roll_state = RollState::default();
+    }
+}
+
+/// Levels spawn in after the player does when moving on to the next one, so follow the start once it shows up
+fn move_player_to_level_start(
+    player_starts: Query<&GridCoords, Added<PlayerStart>>,
+    mut query: Query<
+        (
+            &mut SpawnPointGridPosition,
+            &mut GridPosition,
+            &mut GridMovement,
+        ),
+        With<Player>,
+    >,
+) {
+    let Some(start) = player_starts.iter().next() else {
+        return;
+    };
+    let start = Vec2::new(start.x as f32, start.y as f32);
+    for (mut spawn_point, mut position, mut movement) in &mut query {
+        spawn_point.0 = start;
+        *position = GridPosition::new(start.x, start.y);
+        movement.reset();
+    }
+}
@@ -3,14 +3,16 @@ use std::time::Duration;
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
 
-use crate::game::spawn::health::OnDeath;
-use crate::screen::Screen;
+use crate::game::campaign::LevelStarted;
+use crate::game::end_game::{EndGameCondition, LoseConditions};
+use crate::screen::PlayState;
+use crate::AppSet;
 
 /// Handles threat levels.
 ///
 /// Will signal `ThreatLevelIncreased(u8)` with the new threat level.
-/// Will additionally signal `EndGameCondition::TimeOut` when time ran out
-/// and set AppState to `Screen::GameOver`
+/// Will additionally signal `EndGameCondition::TimeOut` when time ran out on the final threat level,
+/// if `LoseConditions::time_out` is turned on
 pub fn plugin(app: &mut App) {
     let settings = ThreatTimerSettings {
         levels: 3,
@@ -25,11 +27,20 @@ pub fn plugin(app: &mut App) {
     });
     app.insert_resource(PlayStopwatch(Stopwatch::new()));
     app.insert_resource(settings);
-    app.add_systems(Update, tick.run_if(in_state(Screen::Playing)));
-    app.observe(on_death_reset_timer);
+    app.add_systems(
+        FixedUpdate,
+        tick.run_if(in_state(PlayState::Running))
+            .in_set(AppSet::TickTimers),
+    );
+    app.observe(reset_timer);
+
+    // reflection
+    app.register_type::<ThreatTimer>();
+    app.register_type::<PlayStopwatch>();
 }
 
-#[derive(Resource)]
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 /// This stopwatch is started when the game starts
 /// and is used to show highscores
 pub struct PlayStopwatch(pub Stopwatch);
@@ -40,7 +51,8 @@ pub struct PlayStopwatch(pub Stopwatch);
 #[derive(Event)]
 pub struct ThreatLevelIncreased(u8);
 
-#[derive(Resource)]
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 pub struct ThreatTimer {
     pub timer: Timer,
     pub current_level: u8,
@@ -57,19 +69,71 @@ fn tick(
     threat_settings: Res<ThreatTimerSettings>,
     mut threat_timer: ResMut<ThreatTimer>,
     mut play_stopwatch: ResMut<PlayStopwatch>,
+    lose_conditions: Res<LoseConditions>,
     mut commands: Commands,
 ) {
+    play_stopwatch.0.tick(time.delta());
+    threat_timer.timer.tick(time.delta());
+    if !threat_timer.timer.finished() {
+        return;
+    }
+
     if threat_timer.current_level < threat_settings.levels - 1 {
-        threat_timer.timer.tick(time.delta());
-        play_stopwatch.0.tick(time.delta());
-        if threat_timer.timer.finished() {
-            threat_timer.current_level += 1;
-            commands.trigger(ThreatLevelIncreased(threat_timer.current_level));
-        }
+        threat_timer.current_level += 1;
+        commands.trigger(ThreatLevelIncreased(threat_timer.current_level));
+    } else if lose_conditions.time_out {
+        commands.trigger(EndGameCondition::TimeOut);
     }
 }
 
-fn on_death_reset_timer(_trigger: Trigger<OnDeath>, mut threat_timer: ResMut<ThreatTimer>) {
+/// Starting a new level calms everyone back down. Dying rewinds the timer along with the rest of the level.
+fn reset_timer(_trigger: Trigger<LevelStarted>, mut threat_timer: ResMut<ThreatTimer>) {
     threat_timer.current_level = 0;
     threat_timer.timer.reset();
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(false, false ; "off")]
+    #[test_case(true, true ; "on")]
+    fn running_out_of_time_ends_the_game_only_with_a_time_limit(time_out: bool, ends: bool) {
+        #[derive(Resource, Default)]
+        struct TimedOut(bool);
+
+        let settings = ThreatTimerSettings {
+            levels: 3,
+            seconds_between_levels: 1.0,
+        };
+        // already run out on the final level
+        let mut timer = Timer::new(Duration::from_secs(1), TimerMode::Once);
+        timer.tick(Duration::from_secs(1));
+
+        let mut app = App::new();
+        app.init_resource::<Time>();
+        app.insert_resource(ThreatTimer {
+            timer,
+            current_level: settings.levels - 1,
+        });
+        app.insert_resource(settings);
+        app.insert_resource(PlayStopwatch(Stopwatch::new()));
+        app.insert_resource(LoseConditions {
+            time_out,
+            ..default()
+        });
+        app.init_resource::<TimedOut>();
+        app.observe(
+            |trigger: Trigger<EndGameCondition>, mut timed_out: ResMut<TimedOut>| {
+                timed_out.0 |= *trigger.event() == EndGameCondition::TimeOut;
+            },
+        );
+
+        app.world_mut().run_system_once(tick);
+
+        assert_eq!(app.world().resource::<TimedOut>().0, ends);
+    }
+}
@@ -1 +1,2 @@
 pub mod line_segment;
+pub mod visibility_polygon;
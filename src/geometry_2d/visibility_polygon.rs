@@ -0,0 +1,403 @@
+//! The exact area visible from a point among wall segments, as a polygon, optionally limited to a cone and a range.
+//!
+//! Rays are cast from the point at every angle where what's in front of it can change: at each end of every wall and
+//! just either side of it, where walls cross each other or the edge of the range, and at the edges of the cone.
+//! Between two of those angles the nearest wall is always the same one, so joining up where the rays stop gives the
+//! exact outline, apart from the edge of the range, which is followed in short straight steps.
+//!
+//! Walls seen edge-on, walls of no length and walls running through the point itself don't block anything.
+
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use crate::geometry_2d::line_segment::LineSegment;
+
+/// How far either side of the end of a wall to look, to see both the wall and what's behind it, unless it's close
+const GRAZE_ANGLE: f32 = 1e-4;
+
+/// The longest stretch of the edge of the range between two corners of the polygon
+const MAX_ARC_STEP: f32 = PI / 32.;
+
+const EPSILON: f32 = 1e-5;
+
+/// Limits the view to either side of a direction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cone {
+    pub direction: Vec2,
+    /// How far either side of `direction` can be seen, so anything from PI up sees all around
+    pub half_angle: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VisibilityPolygon {
+    /// Where it's seen from
+    pub origin: Vec2,
+    /// The corners of the polygon counter-clockwise, starting with the origin when it's limited to a cone.
+    /// Empty when nothing can be seen.
+    pub vertices: Vec<Vec2>,
+}
+
+impl VisibilityPolygon {
+    /// What can be seen from `origin` up to `range` away, inside `cone` if there is one
+    pub fn new(origin: Vec2, walls: &[LineSegment], range: f32, cone: Option<Cone>) -> Self {
+        let mut polygon = Self {
+            origin,
+            vertices: vec![],
+        };
+        if !range.is_finite() || range <= 0. {
+            return polygon;
+        }
+        let walls: Vec<_> = walls
+            .iter()
+            .filter(|wall| wall.start().distance(wall.end()) > EPSILON)
+            .collect();
+
+        // angles are measured counter-clockwise from the start of the sweep
+        let (start, sweep) = match cone {
+            Some(cone) if cone.half_angle < PI && cone.direction != Vec2::ZERO => {
+                let half_angle = cone.half_angle.max(0.);
+                (cone.direction.to_angle() - half_angle, 2. * half_angle)
+            }
+            _ => (0., TAU),
+        };
+        let all_around = sweep >= TAU;
+        let relative = |point: Vec2| ((point - origin).to_angle() - start).rem_euclid(TAU);
+
+        let arc_steps = (sweep / MAX_ARC_STEP).ceil().max(1.) as usize;
+        let mut angles: Vec<f32> = (0..=arc_steps)
+            // rounding could otherwise push the last one past the edge of the cone
+            .map(|step| (sweep * step as f32 / arc_steps as f32).min(sweep))
+            .collect();
+        for (i, wall) in walls.iter().enumerate() {
+            angles.extend(
+                walls[i + 1..]
+                    .iter()
+                    .filter_map(|other| wall.intersection_point(other))
+                    .filter(|crossing| crossing.distance(origin) > EPSILON)
+                    .map(&relative),
+            );
+            for end in [wall.start(), wall.end()] {
+                let distance = end.distance(origin);
+                if distance <= EPSILON {
+                    continue;
+                }
+                // close ends need a wider angle to pass clear of them
+                let graze = GRAZE_ANGLE.max(2. * EPSILON / distance);
+                let angle = relative(end);
+                angles.extend([angle - graze, angle, angle + graze]);
+            }
+            angles.extend(
+                circle_crossings(wall, origin, range)
+                    .into_iter()
+                    .map(&relative),
+            );
+        }
+        if all_around {
+            for angle in &mut angles {
+                *angle = angle.rem_euclid(TAU);
+            }
+        }
+        angles.retain(|angle| (0. ..=sweep).contains(angle));
+        angles.sort_by(f32::total_cmp);
+        angles.dedup_by(|a, b| (*a - *b).abs() < EPSILON * EPSILON);
+        if all_around
+            && angles
+                .last()
+                .is_some_and(|last| TAU - last < EPSILON * EPSILON)
+        {
+            // the same as the first
+            angles.pop();
+        }
+
+        if !all_around {
+            polygon.vertices.push(origin);
+        }
+        polygon.vertices.extend(angles.into_iter().map(|angle| {
+            let direction = Vec2::from_angle(start + angle);
+            origin + direction * nearest_hit(origin, direction, &walls, range)
+        }));
+        polygon
+    }
+
+    /// Whether the point is inside the polygon. Points right on its edges may go either way.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let mut inside = false;
+        let mut previous = match self.vertices.last() {
+            Some(last) => *last,
+            None => return false,
+        };
+        for &vertex in &self.vertices {
+            if (vertex.y > point.y) != (previous.y > point.y) {
+                let crossing_x = vertex.x
+                    + (point.y - vertex.y) / (previous.y - vertex.y) * (previous.x - vertex.x);
+                if point.x < crossing_x {
+                    inside = !inside;
+                }
+            }
+            previous = vertex;
+        }
+        inside
+    }
+}
+
+/// How far a ray gets before hitting a wall, up to `range`
+fn nearest_hit(origin: Vec2, direction: Vec2, walls: &[&LineSegment], range: f32) -> f32 {
+    walls
+        .iter()
+        .filter_map(|wall| ray_hit(origin, direction, wall))
+        .fold(range, f32::min)
+}
+
+/// How far along the ray it hits the wall, if it does. Walls running alongside the ray or through its start
+/// don't count.
+fn ray_hit(origin: Vec2, direction: Vec2, wall: &LineSegment) -> Option<f32> {
+    let along_wall = wall.end() - wall.start();
+    let denominator = direction.perp_dot(along_wall);
+    if denominator.abs() <= EPSILON * along_wall.length() {
+        return None;
+    }
+    let to_wall = wall.start() - origin;
+    let distance = to_wall.perp_dot(along_wall) / denominator;
+    let across_wall = to_wall.perp_dot(direction) / denominator;
+    // the same distance past either end of every wall, however long it is
+    let tolerance = EPSILON / along_wall.length();
+    (distance > EPSILON && (-tolerance..=1. + tolerance).contains(&across_wall)).then_some(distance)
+}
+
+/// Where the wall crosses the circle of `radius` around `center`
+fn circle_crossings(wall: &LineSegment, center: Vec2, radius: f32) -> Vec<Vec2> {
+    let along_wall = wall.end() - wall.start();
+    let from_center = wall.start() - center;
+    let a = along_wall.length_squared();
+    let b = 2. * from_center.dot(along_wall);
+    let c = from_center.length_squared() - radius * radius;
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return vec![];
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2. * a), (-b + root) / (2. * a)]
+        .into_iter()
+        .filter(|t| (0. ..=1.).contains(t))
+        .map(|t| wall.start() + along_wall * t)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    fn wall(a: (f32, f32), b: (f32, f32)) -> LineSegment {
+        LineSegment::new(Vec2::new(a.0, a.1), Vec2::new(b.0, b.1))
+    }
+
+    #[test]
+    fn sees_all_around_with_nothing_in_the_way() {
+        let polygon = VisibilityPolygon::new(Vec2::ONE, &[], 5., None);
+
+        for vertex in &polygon.vertices {
+            assert!(
+                (vertex.distance(Vec2::ONE) - 5.).abs() < 1e-4,
+                "{:?}",
+                vertex
+            );
+        }
+        assert!(polygon.contains(Vec2::new(5.5, 1.)));
+        assert!(polygon.contains(Vec2::new(1., -3.5)));
+        assert!(!polygon.contains(Vec2::new(6.5, 1.)));
+    }
+
+    #[test]
+    fn walls_cast_shadows() {
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, &[wall((2., -1.), (2., 1.))], 10., None);
+
+        assert!(polygon.contains(Vec2::new(1.9, 0.)));
+        assert!(!polygon.contains(Vec2::new(2.1, 0.)));
+        assert!(!polygon.contains(Vec2::new(8., 3.9)));
+        assert!(polygon.contains(Vec2::new(8., 4.1)));
+    }
+
+    #[test]
+    fn only_sees_inside_the_cone() {
+        let cone = Cone {
+            direction: Vec2::Y,
+            half_angle: PI / 4.,
+        };
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, &[], 10., Some(cone));
+
+        assert_eq!(polygon.vertices[0], Vec2::ZERO);
+        assert!(polygon.contains(Vec2::new(0., 9.)));
+        assert!(polygon.contains(Vec2::new(2.9, 3.)));
+        assert!(!polygon.contains(Vec2::new(3.1, 3.)));
+        assert!(!polygon.contains(Vec2::new(0., -1.)));
+    }
+
+    #[test]
+    fn nothing_in_range_is_empty() {
+        for range in [0., -1., f32::NAN, f32::INFINITY] {
+            let polygon = VisibilityPolygon::new(Vec2::ZERO, &[], range, None);
+
+            assert!(polygon.vertices.is_empty(), "{}", range);
+            assert!(!polygon.contains(Vec2::ZERO));
+        }
+    }
+
+    #[test]
+    fn walls_seen_edge_on_dont_block() {
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, &[wall((2., 0.), (4., 0.))], 10., None);
+
+        assert!(polygon.contains(Vec2::new(5., 0.1)));
+        assert!(polygon.contains(Vec2::new(5., -0.1)));
+    }
+
+    #[test]
+    fn walls_of_no_length_dont_block() {
+        // `LineSegment::new` can't make one, as it has no direction
+        let point = LineSegment {
+            segment2d: Segment2d::new(Dir2::X, 0.),
+            center: Vec2::new(2., 0.),
+        };
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, &[point], 10., None);
+
+        assert!(polygon.contains(Vec2::new(3., 0.01)));
+    }
+
+    #[test]
+    fn walls_through_the_origin_dont_block() {
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, &[wall((0., -3.), (0., 3.))], 10., None);
+
+        assert!(polygon.contains(Vec2::new(-5., 1.)));
+        assert!(polygon.contains(Vec2::new(5., 1.)));
+    }
+
+    #[test]
+    fn overlapping_walls_along_the_same_line_still_block() {
+        let polygon = VisibilityPolygon::new(
+            Vec2::ZERO,
+            &[wall((2., -2.), (2., 1.)), wall((2., -1.), (2., 2.))],
+            10.,
+            None,
+        );
+
+        assert!(!polygon.contains(Vec2::new(3., 0.)));
+        assert!(!polygon.contains(Vec2::new(3., 1.9)));
+    }
+
+    #[test]
+    fn walls_meeting_at_a_corner_leave_no_gap() {
+        let polygon = VisibilityPolygon::new(
+            Vec2::ZERO,
+            &[wall((2., -2.), (2., 2.)), wall((2., 2.), (-2., 2.))],
+            10.,
+            None,
+        );
+
+        assert!(!polygon.contains(Vec2::new(5., 5.)));
+        assert!(!polygon.contains(Vec2::new(3., 3.1)));
+    }
+
+    #[test]
+    fn follows_walls_crossing_each_other() {
+        let polygon = VisibilityPolygon::new(
+            Vec2::ZERO,
+            &[wall((2., -1.), (4., 3.)), wall((4., -1.), (2., 3.))],
+            10.,
+            None,
+        );
+
+        assert!(polygon.contains(Vec2::new(2.97, 1.)));
+        assert!(!polygon.contains(Vec2::new(3.5, 1.)));
+    }
+
+    /// Random walls seen from random places, checking the polygon covers exactly the points in sight.
+    /// Points too close to an edge of the polygon to call either way are skipped.
+    #[test]
+    fn covers_exactly_what_can_be_seen() {
+        let mut rng = ChaCha8Rng::seed_from_u64(24);
+        let point =
+            |rng: &mut ChaCha8Rng| Vec2::new(rng.gen_range(-12. ..12.), rng.gen_range(-12. ..12.));
+
+        for _ in 0..100 {
+            let walls: Vec<_> = (0..8)
+                .map(|_| LineSegment::new(point(&mut rng), point(&mut rng)))
+                .collect();
+            let origin = point(&mut rng);
+            let range = rng.gen_range(3. ..15.);
+            let cone = rng.gen_bool(0.5).then(|| Cone {
+                direction: Vec2::from_angle(rng.gen_range(-PI..PI)),
+                half_angle: rng.gen_range(0.1..PI),
+            });
+            if walls
+                .iter()
+                .any(|wall| distance_to_segment(origin, wall) < 0.1)
+            {
+                continue;
+            }
+
+            let polygon = VisibilityPolygon::new(origin, &walls, range, cone);
+
+            // every corner is in range, and they go round in order
+            let mut previous_angle = f32::NEG_INFINITY;
+            let start = cone.map_or(0., |cone| cone.direction.to_angle() - cone.half_angle);
+            for vertex in &polygon.vertices {
+                assert!(vertex.distance(origin) <= range + 1e-3);
+                if *vertex == origin {
+                    continue;
+                }
+                let mut angle = ((*vertex - origin).to_angle() - start).rem_euclid(TAU);
+                if previous_angle == f32::NEG_INFINITY && angle > PI {
+                    // the first corner is right at the start, but can round to just short of a full turn
+                    angle -= TAU;
+                }
+                assert!(angle + 1e-3 >= previous_angle, "{:?}", polygon);
+                previous_angle = angle;
+            }
+
+            for _ in 0..200 {
+                let target = point(&mut rng);
+                let offset = target - origin;
+                let to_target = LineSegment::new(origin, target);
+                let angle_to = |point: Vec2| (point - origin).angle_between(offset).abs();
+
+                let near_an_edge = (offset.length() - range).abs() < 0.05 * range
+                    || walls.iter().any(|wall| {
+                        distance_to_segment(target, wall) < 0.05
+                            || angle_to(wall.start()) < 0.01
+                            || angle_to(wall.end()) < 0.01
+                    })
+                    || cone.is_some_and(|cone| {
+                        (offset.angle_between(cone.direction).abs() - cone.half_angle).abs() < 0.01
+                    });
+                if near_an_edge {
+                    continue;
+                }
+
+                let in_sight = offset.length() < range
+                    && cone.is_none_or(|cone| {
+                        offset.angle_between(cone.direction).abs() < cone.half_angle
+                    })
+                    && !walls.iter().any(|wall| wall.do_intersect(&to_target));
+                assert_eq!(
+                    polygon.contains(target),
+                    in_sight,
+                    "{:?} from {:?} up to {} in {:?} among {:?}",
+                    target,
+                    origin,
+                    range,
+                    cone,
+                    walls
+                );
+            }
+        }
+    }
+
+    fn distance_to_segment(point: Vec2, segment: &LineSegment) -> f32 {
+        let along = segment.end() - segment.start();
+        let t = ((point - segment.start()).dot(along) / along.length_squared()).clamp(0., 1.);
+        point.distance(segment.start() + along * t)
+    }
+}
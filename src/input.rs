@@ -1,16 +1,27 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
 
 pub fn plugin(app: &mut App) {
     app.add_plugins(InputManagerPlugin::<PlayerAction>::default());
+    app.add_systems(
+        Update,
+        apply_key_bindings.run_if(resource_changed::<Settings>),
+    );
 
     #[cfg(feature = "dev")]
     app.add_plugins(InputManagerPlugin::<DevActionToggles>::default());
 }
 
 // This is the list of "things in the game I want to be able to do based on input"
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
 pub enum PlayerAction {
+    /// Analog movement from a gamepad stick, on top of the digital directions below
+    Move,
     MoveUp,
     MoveDown,
     MoveLeft,
@@ -21,29 +32,185 @@ pub enum PlayerAction {
     ZoomToOverview,
 }
 impl PlayerAction {
-    /// Define the default bindings to the input
-    pub fn default_input_map() -> InputMap<Self> {
+    /// Actions players can bind to a button of their choice in the settings
+    pub const REBINDABLE: [Self; 8] = [
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::Roll,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::ZoomToOverview,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Move => "Move",
+            Self::MoveUp => "Move up",
+            Self::MoveDown => "Move down",
+            Self::MoveLeft => "Move left",
+            Self::MoveRight => "Move right",
+            Self::Roll => "Roll",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::ZoomToOverview => "Overview",
+        }
+    }
+
+    /// Define the bindings to the input, from both device profiles picked in the settings
+    pub fn input_map_for(controls: &ControlProfiles) -> InputMap<Self> {
         let mut input_map = InputMap::default();
 
-        input_map.insert(Self::MoveUp, KeyCode::KeyW);
-        input_map.insert(Self::MoveUp, KeyCode::ArrowUp);
+        for profile in [&controls.keyboard, &controls.gamepad] {
+            for (action, input) in &profile.bindings {
+                input_map.insert(*action, *input);
+            }
+        }
 
-        input_map.insert(Self::MoveDown, KeyCode::KeyS);
-        input_map.insert(Self::MoveDown, KeyCode::ArrowDown);
+        for (action, input) in Self::fixed_bindings() {
+            input_map.insert(action, input);
+        }
 
-        input_map.insert(Self::MoveLeft, KeyCode::KeyA);
-        input_map.insert(Self::MoveLeft, KeyCode::ArrowLeft);
+        input_map
+    }
 
-        input_map.insert(Self::MoveRight, KeyCode::KeyD);
-        input_map.insert(Self::MoveRight, KeyCode::ArrowRight);
+    /// Bindings that always work, whatever else is bound, so they can't be rebound to anything else
+    pub fn fixed_bindings() -> [(Self, InputKind); 5] {
+        [
+            (Self::MoveUp, KeyCode::ArrowUp.into()),
+            (Self::MoveDown, KeyCode::ArrowDown.into()),
+            (Self::MoveLeft, KeyCode::ArrowLeft.into()),
+            (Self::MoveRight, KeyCode::ArrowRight.into()),
+            (Self::Move, DualAxis::left_stick().into()),
+        ]
+    }
+}
 
-        // camera
-        input_map.insert(Self::ZoomToOverview, KeyCode::Space);
+/// The kinds of device players can set up controls for
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputDevice {
+    Keyboard,
+    Gamepad,
+}
 
-        // roll
-        input_map.insert(Self::Roll, KeyCode::ShiftLeft);
+impl InputDevice {
+    pub const ALL: [Self; 2] = [Self::Keyboard, Self::Gamepad];
 
-        input_map
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Keyboard => "Keyboard",
+            Self::Gamepad => "Gamepad",
+        }
+    }
+}
+
+/// Player bindings for each kind of device, saved with the rest of the settings
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ControlProfiles {
+    pub keyboard: ControlProfile,
+    pub gamepad: ControlProfile,
+}
+
+impl Default for ControlProfiles {
+    fn default() -> Self {
+        Self {
+            keyboard: ControlProfile::default_for(InputDevice::Keyboard),
+            gamepad: ControlProfile::default_for(InputDevice::Gamepad),
+        }
+    }
+}
+
+impl ControlProfiles {
+    pub fn profile(&self, device: InputDevice) -> &ControlProfile {
+        match device {
+            InputDevice::Keyboard => &self.keyboard,
+            InputDevice::Gamepad => &self.gamepad,
+        }
+    }
+
+    pub fn profile_mut(&mut self, device: InputDevice) -> &mut ControlProfile {
+        match device {
+            InputDevice::Keyboard => &mut self.keyboard,
+            InputDevice::Gamepad => &mut self.gamepad,
+        }
+    }
+}
+
+/// Which button on one device triggers each rebindable action
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ControlProfile {
+    bindings: HashMap<PlayerAction, InputKind>,
+}
+
+impl ControlProfile {
+    pub fn default_for(device: InputDevice) -> Self {
+        let bindings: [(PlayerAction, InputKind); 8] = match device {
+            InputDevice::Keyboard => [
+                (PlayerAction::MoveUp, KeyCode::KeyW.into()),
+                (PlayerAction::MoveDown, KeyCode::KeyS.into()),
+                (PlayerAction::MoveLeft, KeyCode::KeyA.into()),
+                (PlayerAction::MoveRight, KeyCode::KeyD.into()),
+                (PlayerAction::Roll, KeyCode::ShiftLeft.into()),
+                (PlayerAction::ZoomIn, KeyCode::Equal.into()),
+                (PlayerAction::ZoomOut, KeyCode::Minus.into()),
+                (PlayerAction::ZoomToOverview, KeyCode::Space.into()),
+            ],
+            InputDevice::Gamepad => [
+                (PlayerAction::MoveUp, GamepadButtonType::DPadUp.into()),
+                (PlayerAction::MoveDown, GamepadButtonType::DPadDown.into()),
+                (PlayerAction::MoveLeft, GamepadButtonType::DPadLeft.into()),
+                (PlayerAction::MoveRight, GamepadButtonType::DPadRight.into()),
+                (PlayerAction::Roll, GamepadButtonType::South.into()),
+                (PlayerAction::ZoomIn, GamepadButtonType::RightTrigger.into()),
+                (PlayerAction::ZoomOut, GamepadButtonType::LeftTrigger.into()),
+                (
+                    PlayerAction::ZoomToOverview,
+                    GamepadButtonType::North.into(),
+                ),
+            ],
+        };
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+
+    pub fn get(&self, action: PlayerAction) -> Option<InputKind> {
+        self.bindings.get(&action).copied()
+    }
+
+    /// Bind an action to a new input. If another action already uses that input, either in this profile or in
+    /// [`PlayerAction::fixed_bindings`], nothing changes and the action it clashes with is returned instead.
+    pub fn rebind(&mut self, action: PlayerAction, input: InputKind) -> Result<(), PlayerAction> {
+        let conflict = self
+            .bindings
+            .iter()
+            .map(|(other, bound)| (*other, *bound))
+            .chain(PlayerAction::fixed_bindings())
+            .find(|(other, bound)| *other != action && *bound == input);
+        if let Some((other, _)) = conflict {
+            return Err(other);
+        }
+        self.bindings.insert(action, input);
+        Ok(())
+    }
+}
+
+/// A short name for an input to show in menus
+pub fn input_name(input: &InputKind) -> String {
+    let name = match input {
+        InputKind::PhysicalKey(key) => format!("{:?}", key),
+        InputKind::GamepadButton(button) => format!("{:?}", button),
+        other => format!("{:?}", other),
+    };
+    name.trim_start_matches("Key")
+        .trim_start_matches("Digit")
+        .to_string()
+}
+
+fn apply_key_bindings(settings: Res<Settings>, mut input_maps: Query<&mut InputMap<PlayerAction>>) {
+    for mut input_map in &mut input_maps {
+        *input_map = PlayerAction::input_map_for(&settings.controls);
     }
 }
 
@@ -54,6 +221,8 @@ pub enum DevActionToggles {
     FogOfWar,
     DebugOverlays,
     KillPlayer,
+    ExportGhost,
+    ImportGhost,
 }
 
 #[cfg(feature = "dev")]
@@ -65,7 +234,79 @@ impl DevActionToggles {
         input_map.insert(Self::FogOfWar, KeyCode::F2);
         input_map.insert(Self::DebugOverlays, KeyCode::F3);
         input_map.insert(Self::KillPlayer, KeyCode::F4);
+        input_map.insert(Self::ExportGhost, KeyCode::F5);
+        input_map.insert(Self::ImportGhost, KeyCode::F6);
 
         input_map
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_to_a_free_input() {
+        let mut profile = ControlProfile::default_for(InputDevice::Keyboard);
+
+        let result = profile.rebind(PlayerAction::Roll, KeyCode::KeyE.into());
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(profile.get(PlayerAction::Roll), Some(KeyCode::KeyE.into()));
+    }
+
+    #[test]
+    fn rebinding_to_a_used_input_is_a_conflict() {
+        let mut profile = ControlProfile::default_for(InputDevice::Gamepad);
+
+        let result = profile.rebind(PlayerAction::Roll, GamepadButtonType::North.into());
+
+        assert_eq!(result, Err(PlayerAction::ZoomToOverview));
+        assert_eq!(
+            profile.get(PlayerAction::Roll),
+            Some(GamepadButtonType::South.into())
+        );
+    }
+
+    #[test]
+    fn rebinding_to_a_fixed_input_is_a_conflict() {
+        let mut profile = ControlProfile::default_for(InputDevice::Keyboard);
+
+        let result = profile.rebind(PlayerAction::Roll, KeyCode::ArrowUp.into());
+
+        assert_eq!(result, Err(PlayerAction::MoveUp));
+        assert_eq!(
+            profile.get(PlayerAction::Roll),
+            Some(KeyCode::ShiftLeft.into())
+        );
+    }
+
+    #[test]
+    fn rebinding_to_the_actions_own_fixed_input() {
+        let mut profile = ControlProfile::default_for(InputDevice::Keyboard);
+
+        let result = profile.rebind(PlayerAction::MoveUp, KeyCode::ArrowUp.into());
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn rebinding_to_the_same_input_is_not_a_conflict() {
+        let mut profile = ControlProfile::default_for(InputDevice::Keyboard);
+
+        assert_eq!(
+            profile.rebind(PlayerAction::MoveUp, KeyCode::KeyW.into()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn every_rebindable_action_has_a_default_on_every_device() {
+        for device in InputDevice::ALL {
+            let profile = ControlProfile::default_for(device);
+            for action in PlayerAction::REBINDABLE {
+                assert!(profile.get(action).is_some(), "{:?} {:?}", device, action);
+            }
+        }
+    }
+}
@@ -1,10 +1,13 @@
-use bevy::window::WindowResolution;
-use bevy::{
-    asset::AssetMetaCheck,
-    audio::{AudioPlugin, Volume},
-    prelude::*,
-};
-use bevy_ecs_ldtk::{LdtkPlugin, LdtkWorldBundle, LevelSelection};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::window::{ExitCondition, WindowResolution};
+use bevy::winit::WinitPlugin;
+use bevy::{asset::AssetMetaCheck, prelude::*};
+use bevy_ecs_ldtk::{LdtkPlugin, LdtkWorldBundle};
 
 #[cfg(feature = "dev")]
 mod dev_tools;
@@ -15,31 +18,56 @@ mod ui;
 pub mod geometry_2d;
 
 mod input;
+mod settings;
 #[cfg(test)]
 pub mod testing;
 
-pub struct AppPlugin;
+#[derive(Default, Debug, PartialEq)]
+pub struct AppPlugin {
+    /// Runs without a window or rendering, e.g. to replay a session on a machine without a display
+    pub headless: bool,
+    /// Records the player's input to this file
+    pub record: Option<PathBuf>,
+    /// Plays back the input recorded in this file instead of the player's, quitting afterwards if headless
+    pub replay: Option<PathBuf>,
+    /// Seeds gameplay randomness, so everyone playing with the same seed gets the same run
+    pub seed: Option<u64>,
+}
+
+impl AppPlugin {
+    /// Reads the command line: `--record <file>`, `--replay <file>`, `--seed <number>` and `--headless`
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut plugin = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => plugin.headless = true,
+                "--record" => {
+                    plugin.record = Some(args.next().ok_or("--record needs a file")?.into());
+                }
+                "--replay" => {
+                    plugin.replay = Some(args.next().ok_or("--replay needs a file")?.into());
+                }
+                "--seed" => {
+                    let seed = args.next().ok_or("--seed needs a number")?;
+                    plugin.seed = Some(
+                        seed.parse()
+                            .map_err(|_| format!("{} isn't a valid seed", seed))?,
+                    );
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        Ok(plugin)
+    }
+}
 
 impl Plugin for AppPlugin {
     fn build(&self, app: &mut App) {
-        // Order new `AppStep` variants by adding them here:
-        app.configure_sets(
-            Update,
-            (
-                AppSet::TickTimers,
-                AppSet::RecordInput,
-                AppSet::UpdateVirtualGrid,
-                AppSet::Update,
-                AppSet::UpdateAi,
-                AppSet::UpdateWorld,
-                AppSet::UpdateFog,
-            )
-                .chain(),
-        );
+        configure_app_sets(app);
 
         // Spawn the main camera.
         app.add_systems(Startup, spawn_ldtk_world_bundle);
-        app.insert_resource(LevelSelection::index(0));
 
         // Set background color
         let rgb = (37., 19., 26.);
@@ -48,16 +76,38 @@ impl Plugin for AppPlugin {
         app.insert_resource(ClearColor(color));
 
         // Add Bevy plugins.
-        app.add_plugins(
-            DefaultPlugins
-                .set(AssetPlugin {
-                    // Wasm builds will check for meta files (that don't exist) if this isn't set.
-                    // This causes errors and even panics on web build on itch.
-                    // See https://github.com/bevyengine/bevy_github_ci_template/issues/48.
-                    meta_check: AssetMetaCheck::Never,
-                    ..default()
-                })
-                .set(WindowPlugin {
+        let default_plugins = DefaultPlugins
+            .set(AssetPlugin {
+                // Wasm builds will check for meta files (that don't exist) if this isn't set.
+                // This causes errors and even panics on web build on itch.
+                // See https://github.com/bevyengine/bevy_github_ci_template/issues/48.
+                meta_check: AssetMetaCheck::Never,
+                ..default()
+            })
+            // to avoid blurry pixels
+            .set(ImagePlugin::default_nearest());
+        if self.headless {
+            app.add_plugins((
+                default_plugins
+                    .set(WindowPlugin {
+                        primary_window: None,
+                        exit_condition: ExitCondition::DontExit,
+                        ..default()
+                    })
+                    .set(RenderPlugin {
+                        render_creation: WgpuSettings {
+                            backends: None,
+                            ..default()
+                        }
+                        .into(),
+                        ..default()
+                    })
+                    .disable::<WinitPlugin>(),
+                ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1.0 / 60.0)),
+            ));
+        } else {
+            app.add_plugins(
+                default_plugins.set(WindowPlugin {
                     primary_window: Window {
                         title: "bevy-jam-5".to_string(),
                         canvas: Some("#bevy".to_string()),
@@ -68,33 +118,71 @@ impl Plugin for AppPlugin {
                     }
                     .into(),
                     ..default()
-                })
-                .set(AudioPlugin {
-                    global_volume: GlobalVolume {
-                        volume: Volume::new(0.3), // quiet audio
-                    },
-                    ..default()
-                })
-                // to avoid blurry pixels
-                .set(ImagePlugin::default_nearest()),
-        );
+                }),
+            );
+        }
 
         app.add_plugins(LdtkPlugin);
 
         // Add other plugins.
+        app.add_plugins(settings::plugin);
         app.add_plugins((game::plugin, screen::plugin, ui::plugin));
         app.add_plugins(game::ai::plugin);
         app.add_plugins(input::plugin);
 
+        // Record or replay a session, if asked to on the command line. Replays bring their own seed.
+        if let Some(seed) = self.seed {
+            app.insert_resource(game::session::SessionSeed(seed));
+        }
+        if let Some(path) = &self.record {
+            game::session::record_to(app, path.clone());
+        }
+        if let Some(path) = &self.replay {
+            game::session::replay_from(app, path, self.headless);
+        }
+
         // Enable dev tools for dev builds.
         #[cfg(feature = "dev")]
         app.add_plugins(dev_tools::plugin);
     }
 }
 
-/// High-level groupings of systems for the app in the `Update` schedule.
-/// When adding a new variant, make sure to order it in the `configure_sets`
-/// call above.
+/// Orders the [`AppSet`]s in both schedules they're used in
+fn configure_app_sets(app: &mut App) {
+    // Order new `AppStep` variants by adding them here:
+    app.configure_sets(
+        Update,
+        (
+            AppSet::TickTimers,
+            AppSet::RecordInput,
+            AppSet::UpdateVirtualGrid,
+            AppSet::Update,
+            AppSet::UpdateAi,
+            AppSet::UpdateWorld,
+            AppSet::UpdateFog,
+        )
+            .chain(),
+    );
+    // The simulation runs on fixed ticks, so the same inputs always play out the same way
+    app.configure_sets(
+        FixedUpdate,
+        (
+            AppSet::TickTimers,
+            AppSet::RecordInput,
+            AppSet::UpdateVirtualGrid,
+            AppSet::Update,
+            AppSet::UpdateAi,
+            AppSet::UpdateWorld,
+            AppSet::Collide,
+        )
+            .chain(),
+    );
+}
+
+/// High-level groupings of systems for the app in the `Update` and `FixedUpdate` schedules.
+/// Gameplay simulation (movement, AI, collision) goes in `FixedUpdate`, presentation in `Update`.
+/// When adding a new variant, make sure to order it in
+/// [`configure_app_sets`].
 #[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum AppSet {
     /// Tick timers.
@@ -109,6 +197,8 @@ enum AppSet {
     UpdateAi,
     /// After all grid coordinates are settled, we translate them to real pixels in world space
     UpdateWorld,
+    /// Things bump into each other, once they've been put where their grid positions say
+    Collide,
     /// Fog updates happen last
     UpdateFog,
 }
@@ -123,3 +213,33 @@ fn spawn_ldtk_world_bundle(mut commands: Commands, asset_server: Res<AssetServer
         },
     ));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<AppPlugin, String> {
+        AppPlugin::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_session_flags() {
+        assert_eq!(
+            args(&["--replay", "bug.session", "--headless"]),
+            Ok(AppPlugin {
+                headless: true,
+                record: None,
+                replay: Some("bug.session".into()),
+                seed: None,
+            })
+        );
+        assert_eq!(args(&[]), Ok(AppPlugin::default()));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(args(&["--record"]).is_err());
+        assert!(args(&["--fullscreen"]).is_err());
+        assert!(args(&["--seed", "tuesday"]).is_err());
+    }
+}
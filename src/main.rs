@@ -5,5 +5,13 @@ use bevy::prelude::*;
 use bevy_jam_5::AppPlugin;
 
 fn main() -> AppExit {
-    App::new().add_plugins(AppPlugin).run()
+    let app_plugin = match AppPlugin::from_args(std::env::args().skip(1)) {
+        Ok(app_plugin) => app_plugin,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("usage: bevy-jam-5 [--record <file>] [--replay <file>] [--seed <number>] [--headless]");
+            return AppExit::error();
+        }
+    };
+    App::new().add_plugins(app_plugin).run()
 }
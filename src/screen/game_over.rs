@@ -1,7 +1,11 @@
 use bevy::prelude::*;
 
 use crate::{
-    game::{end_game::EndGameCondition, threat::PlayStopwatch},
+    game::{
+        campaign::Campaign,
+        end_game::{EndGameCondition, RunStats},
+        threat::PlayStopwatch,
+    },
     ui::prelude::*,
 };
 
@@ -23,6 +27,9 @@ pub fn plugin(app: &mut App) {
 pub enum EndGame {
     #[default]
     Win,
+    TimeOut,
+    OutOfLives,
+    Caught,
 }
 
 fn end_game(
@@ -33,6 +40,9 @@ fn end_game(
     next_screen.set(Screen::GameOver);
     let substate = match _trigger.event() {
         EndGameCondition::Win => EndGame::Win,
+        EndGameCondition::TimeOut => EndGame::TimeOut,
+        EndGameCondition::OutOfLives => EndGame::OutOfLives,
+        EndGameCondition::Caught => EndGame::Caught,
     };
     next_substate.set(substate);
 }
@@ -47,16 +57,39 @@ fn enter_game_over(
     mut commands: Commands,
     substate: Res<State<EndGame>>,
     time: Res<PlayStopwatch>,
+    campaign: Res<Campaign>,
+    stats: Res<RunStats>,
 ) {
     commands
         .ui_root()
         .insert(StateScoped(Screen::GameOver))
         .with_children(|children| {
-            let text = "You won!";
+            let text = match substate.get() {
+                EndGame::Win => "You won!",
+                EndGame::TimeOut => "Time's up!",
+                EndGame::OutOfLives => "Out of lives!",
+                EndGame::Caught => "Caught!",
+            };
             children.header(text);
             if *substate.get() == EndGame::Win {
                 children.label(format!("Your time was {}s", time.0.elapsed_secs()));
+                if campaign.splits.len() > 1 {
+                    for (level, split) in campaign.splits.iter().enumerate() {
+                        children.label(format!("Level {}: {:.2}s", level + 1, split.as_secs_f32()));
+                    }
+                }
+            } else {
+                children.label(format!(
+                    "You made it to level {} of {}",
+                    campaign.current_level + 1,
+                    campaign.level_count.max(1)
+                ));
+                children.label(format!("You lasted {:.2}s", time.0.elapsed_secs()));
             }
+            children.label(format!(
+                "Deaths: {}  Times spotted: {}",
+                stats.deaths, stats.detections
+            ));
 
             children.button("Back").insert(GameOverAction::Back);
         });
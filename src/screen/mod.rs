@@ -2,21 +2,29 @@
 mod credits;
 mod game_over;
 mod loading;
+mod pause;
 mod playing;
+mod settings;
 mod title;
 
 use bevy::prelude::*;
 use game_over::EndGame;
+pub use pause::PlayState;
 
 pub(super) fn plugin(app: &mut App) {
-    app.init_state::<Screen>().add_sub_state::<EndGame>();
+    app.init_state::<Screen>()
+        .add_sub_state::<EndGame>()
+        .add_sub_state::<PlayState>();
     app.enable_state_scoped_entities::<Screen>();
+    app.enable_state_scoped_entities::<PlayState>();
 
     app.add_plugins((
         loading::plugin,
         title::plugin,
         credits::plugin,
         playing::plugin,
+        pause::plugin,
+        settings::plugin,
         game_over::plugin,
     ));
 }
@@ -28,6 +36,7 @@ pub enum Screen {
     Loading,
     Title,
     Credits,
+    Settings,
     Playing,
     GameOver,
 }
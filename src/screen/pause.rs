@@ -0,0 +1,155 @@
+//! Pausing the game while playing. Virtual time stands still while paused, which freezes everything that
+//! ticks on it: the whole simulation in `FixedUpdate` (movement, the AI, collisions, the threat timer, and ghost
+//! recording and replay).
+
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use super::settings::{spawn_settings_menu, Rebinding};
+use super::Screen;
+use crate::game::campaign::RestartLevel;
+#[cfg(not(target_family = "wasm"))]
+use crate::game::ghost::{ExportGhost, ImportGhost, SHARED_GHOST_PATH};
+use crate::settings::Settings;
+use crate::ui::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnExit(PlayState::Running), pause_time);
+    app.add_systems(OnEnter(PlayState::Running), unpause_time);
+    app.add_systems(OnExit(Screen::Playing), unpause_time);
+    app.add_systems(OnEnter(PlayState::Paused), enter_pause_menu);
+    app.add_systems(OnEnter(PlayState::Settings), enter_settings_menu);
+
+    app.register_type::<PauseAction>();
+    app.add_systems(
+        Update,
+        (
+            handle_pause_action
+                .run_if(in_state(PlayState::Paused).or_else(in_state(PlayState::Settings))),
+            // escape cancels rebinding a control instead, while that's going on
+            toggle_pause.run_if(
+                input_just_pressed(KeyCode::Escape).and_then(not(resource_exists::<Rebinding>)),
+            ),
+        ),
+    );
+}
+
+/// Whether the game is running or paused, while on the playing screen.
+#[derive(SubStates, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[source(Screen = Screen::Playing)]
+pub enum PlayState {
+    #[default]
+    Running,
+    Paused,
+    Settings,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum PauseAction {
+    Resume,
+    RestartLevel,
+    Settings,
+    Back,
+    QuitToTitle,
+    /// Save this attempt as a ghost to share, see [`SHARED_GHOST_PATH`]
+    #[cfg(not(target_family = "wasm"))]
+    ExportGhost,
+    /// Race a shared ghost, if it was recorded in this level
+    #[cfg(not(target_family = "wasm"))]
+    ImportGhost,
+}
+
+/// Dims the game behind the menu
+const PAUSE_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn enter_pause_menu(mut commands: Commands) {
+    commands
+        .ui_root()
+        .insert((
+            StateScoped(PlayState::Paused),
+            BackgroundColor(PAUSE_BACKGROUND),
+        ))
+        .with_children(|children| {
+            children.header("Paused");
+            children.button("Resume").insert(PauseAction::Resume);
+            children.button("Restart").insert(PauseAction::RestartLevel);
+            children.button("Settings").insert(PauseAction::Settings);
+            #[cfg(not(target_family = "wasm"))]
+            {
+                children
+                    .button("Share ghost")
+                    .insert(PauseAction::ExportGhost);
+                children
+                    .button("Load ghost")
+                    .insert(PauseAction::ImportGhost);
+            }
+            children.button("Quit").insert(PauseAction::QuitToTitle);
+        });
+}
+
+fn enter_settings_menu(mut commands: Commands, settings: Res<Settings>) {
+    commands
+        .ui_root()
+        .insert((
+            StateScoped(PlayState::Settings),
+            BackgroundColor(PAUSE_BACKGROUND),
+        ))
+        .with_children(|children| {
+            spawn_settings_menu(children, &settings);
+            children.button("Back").insert(PauseAction::Back);
+        });
+}
+
+fn toggle_pause(
+    state: Option<Res<State<PlayState>>>,
+    mut next_state: ResMut<NextState<PlayState>>,
+) {
+    let Some(state) = state else {
+        return;
+    };
+    next_state.set(match state.get() {
+        PlayState::Running => PlayState::Paused,
+        PlayState::Paused => PlayState::Running,
+        PlayState::Settings => PlayState::Paused,
+    });
+}
+
+fn handle_pause_action(
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut next_state: ResMut<NextState<PlayState>>,
+    mut button_query: InteractionQuery<&PauseAction>,
+    mut commands: Commands,
+) {
+    for (interaction, action) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            match action {
+                PauseAction::Resume => next_state.set(PlayState::Running),
+                PauseAction::RestartLevel => {
+                    commands.trigger(RestartLevel);
+                    next_state.set(PlayState::Running);
+                }
+                PauseAction::Settings => next_state.set(PlayState::Settings),
+                PauseAction::Back => next_state.set(PlayState::Paused),
+                PauseAction::QuitToTitle => next_screen.set(Screen::Title),
+                #[cfg(not(target_family = "wasm"))]
+                PauseAction::ExportGhost => {
+                    commands.trigger(ExportGhost(SHARED_GHOST_PATH.into()));
+                }
+                #[cfg(not(target_family = "wasm"))]
+                PauseAction::ImportGhost => {
+                    commands.trigger(ImportGhost(SHARED_GHOST_PATH.into()));
+                    next_state.set(PlayState::Running);
+                }
+            }
+        }
+    }
+}
@@ -2,6 +2,8 @@
 
 use bevy::prelude::*;
 
+use crate::game::end_game::{LoseConditions, RunStats};
+use crate::game::ghost::personal_best::PersonalBestDelta;
 use crate::game::threat::ThreatTimer;
 use crate::game::{audio::soundtrack::Soundtrack, spawn::level::SpawnLevel};
 use crate::ui::prelude::*;
@@ -55,21 +57,34 @@ fn exit_playing(mut commands: Commands) {
 fn update_timer(
     threat_settings: Res<crate::game::threat::ThreatTimerSettings>,
     threat_timer: Res<ThreatTimer>,
+    lose_conditions: Res<LoseConditions>,
+    stats: Res<RunStats>,
+    personal_best_delta: Res<PersonalBestDelta>,
     mut query: Query<&mut Text, With<PlayTime>>,
 ) {
     if let Ok(mut text) = query.get_single_mut() {
-        if threat_timer.current_level < threat_settings.levels - 1 {
-            let time = threat_timer.timer.remaining();
-            let seconds = time.as_secs() % 60;
-            let millis = ((time.as_millis() as f32 % 1000.0) / 100.0).floor();
-            text.sections[0].value = format!(
+        let time = threat_timer.timer.remaining();
+        let seconds = time.as_secs() % 60;
+        let millis = ((time.as_millis() as f32 % 1000.0) / 100.0).floor();
+        let mut value = if threat_timer.current_level < threat_settings.levels - 1 {
+            format!(
                 "THREAT LEVEL {}\n(next level in {}.{}s)",
                 threat_timer.current_level + 1,
                 seconds,
                 millis,
-            );
+            )
+        } else if lose_conditions.time_out {
+            format!("RUN FOR YOUR LIFE!\n(time's up in {}.{}s)", seconds, millis)
         } else {
-            text.sections[0].value = "RUN FOR YOUR LIFE!".to_string();
+            "RUN FOR YOUR LIFE!".to_string()
+        };
+        if let Some(lives) = lose_conditions.lives_left(&stats) {
+            value.push_str(&format!("\nLIVES: {}", lives));
         }
+        if let Some(delta) = personal_best_delta.0 {
+            let ahead_or_behind = if delta > 0.0 { "behind" } else { "ahead" };
+            value.push_str(&format!("\nBEST: {:.1}s {}", delta.abs(), ahead_or_behind));
+        }
+        text.sections[0].value = value;
     }
 }
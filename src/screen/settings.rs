@@ -0,0 +1,427 @@
+//! The settings menu, reachable from the title screen and from the pause menu while playing.
+//! Changes apply straight away, and get saved when leaving the menu.
+
+use bevy::prelude::*;
+use bevy::ui::Val::*;
+use leafwing_input_manager::prelude::InputKind;
+
+use super::{PlayState, Screen};
+use crate::input::{input_name, InputDevice, PlayerAction};
+use crate::settings::Settings;
+use crate::ui::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SettingsPage>();
+    app.add_systems(
+        OnEnter(Screen::Settings),
+        (reset_settings_page, enter_settings).chain(),
+    );
+    app.add_systems(OnEnter(PlayState::Settings), reset_settings_page);
+    app.add_systems(OnExit(Screen::Settings), (stop_rebinding, save_settings));
+    app.add_systems(OnExit(PlayState::Settings), (stop_rebinding, save_settings));
+
+    app.register_type::<SettingsAction>();
+    app.add_systems(
+        Update,
+        (
+            apply_setting_changes,
+            follow_page_links,
+            switch_settings_page.run_if(resource_changed::<SettingsPage>),
+            (start_rebinding, capture_rebinding).chain(),
+            handle_settings_action.run_if(in_state(Screen::Settings)),
+        ),
+    );
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum SettingsAction {
+    Back,
+}
+
+/// Which part of the settings is on screen. There are too many to fit on one page.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SettingsPage {
+    #[default]
+    General,
+    /// Bindings for one kind of device
+    Controls(InputDevice),
+    /// How a run can be lost
+    Rules,
+}
+
+/// A button that opens another page of the settings
+#[derive(Component, Debug, Clone, Copy)]
+struct SettingsPageLink(SettingsPage);
+
+/// Holds the widgets of the current settings page
+#[derive(Component)]
+struct SettingsMenu;
+
+/// Which setting a widget in the menu changes
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingField {
+    SoundEnabled,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    DefaultZoom,
+    FollowSnappiness,
+    ControlDevice,
+    TimeOut,
+    MaxDeaths,
+    OneDetection,
+}
+
+/// The choices of how many lives a run gets, `None` being as many as the player likes
+const MAX_DEATHS_OPTIONS: [Option<u32>; 5] = [None, Some(1), Some(3), Some(5), Some(9)];
+
+fn max_deaths_name(max_deaths: Option<u32>) -> String {
+    max_deaths.map_or_else(|| "Unlimited".to_string(), |lives| lives.to_string())
+}
+
+/// A button that rebinds an action when pressed
+#[derive(Component, Debug, Clone, Copy)]
+struct RebindButton(PlayerAction);
+
+/// Tells the player what's going on while rebinding
+#[derive(Component)]
+struct RebindStatus;
+
+const REBIND_HINT: &str = "Click an action to rebind it";
+
+/// Present while waiting for the player to press the input they want to use for an action
+#[derive(Resource, Debug)]
+pub(super) struct Rebinding {
+    device: InputDevice,
+    action: PlayerAction,
+}
+
+fn enter_settings(mut commands: Commands, settings: Res<Settings>) {
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Settings))
+        .with_children(|children| {
+            spawn_settings_menu(children, &settings);
+            children.button("Back").insert(SettingsAction::Back);
+        });
+}
+
+/// Spawns the first page of the settings. Whoever opens the menu adds their own way back out.
+pub(super) fn spawn_settings_menu(children: &mut ChildBuilder, settings: &Settings) {
+    children.header("Settings");
+    children
+        .spawn((
+            Name::new("Settings Menu"),
+            SettingsMenu,
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            spawn_settings_page(children, settings, SettingsPage::General);
+        });
+}
+
+fn spawn_settings_page(children: &mut ChildBuilder, settings: &Settings, page: SettingsPage) {
+    match page {
+        SettingsPage::General => {
+            children
+                .toggle("Sound", settings.sound_enabled)
+                .insert(SettingField::SoundEnabled);
+            children
+                .slider(
+                    "Master volume",
+                    Slider::new(settings.master_volume, 0.0, 1.0),
+                )
+                .insert(SettingField::MasterVolume);
+            children
+                .slider("Music volume", Slider::new(settings.music_volume, 0.0, 1.0))
+                .insert(SettingField::MusicVolume);
+            children
+                .slider("Effects volume", Slider::new(settings.sfx_volume, 0.0, 1.0))
+                .insert(SettingField::SfxVolume);
+
+            children
+                .slider("Zoom", Slider::new(settings.default_zoom, 0.1, 0.7))
+                .insert(SettingField::DefaultZoom);
+            children
+                .slider(
+                    "Camera snappiness",
+                    Slider::new(settings.follow_snappiness, 1.0, 20.0),
+                )
+                .insert(SettingField::FollowSnappiness);
+
+            children
+                .button("Controls")
+                .insert(SettingsPageLink(SettingsPage::Controls(
+                    InputDevice::Keyboard,
+                )));
+            children
+                .button("Rules")
+                .insert(SettingsPageLink(SettingsPage::Rules));
+        }
+        SettingsPage::Rules => {
+            let rules = &settings.lose_conditions;
+            children
+                .toggle("Time limit", rules.time_out)
+                .insert(SettingField::TimeOut);
+            children
+                .cycle(
+                    "Lives",
+                    MAX_DEATHS_OPTIONS
+                        .iter()
+                        .map(|max_deaths| max_deaths_name(*max_deaths))
+                        .collect(),
+                    MAX_DEATHS_OPTIONS
+                        .iter()
+                        .position(|max_deaths| *max_deaths == rules.max_deaths)
+                        .unwrap_or_default(),
+                )
+                .insert(SettingField::MaxDeaths);
+            children
+                .toggle("Caught on sight", rules.one_detection)
+                .insert(SettingField::OneDetection);
+            children.label("Takes effect from the next run");
+
+            children
+                .button("Done")
+                .insert(SettingsPageLink(SettingsPage::General));
+        }
+        SettingsPage::Controls(device) => {
+            children
+                .cycle(
+                    "Device",
+                    InputDevice::ALL
+                        .iter()
+                        .map(|device| device.name().to_string())
+                        .collect(),
+                    InputDevice::ALL
+                        .iter()
+                        .position(|d| *d == device)
+                        .unwrap_or_default(),
+                )
+                .insert(SettingField::ControlDevice);
+
+            for action in PlayerAction::REBINDABLE {
+                children
+                    .choice(action.name(), bound_input_name(settings, device, action))
+                    .insert(RebindButton(action));
+            }
+            children.label(REBIND_HINT).insert(RebindStatus);
+
+            children
+                .button("Done")
+                .insert(SettingsPageLink(SettingsPage::General));
+        }
+    }
+}
+
+fn bound_input_name(settings: &Settings, device: InputDevice, action: PlayerAction) -> String {
+    settings
+        .controls
+        .profile(device)
+        .get(action)
+        .map_or_else(|| "-".to_string(), |input| input_name(&input))
+}
+
+fn reset_settings_page(mut page: ResMut<SettingsPage>) {
+    page.set_if_neq(SettingsPage::General);
+}
+
+fn follow_page_links(
+    mut button_query: InteractionQuery<&SettingsPageLink>,
+    mut page: ResMut<SettingsPage>,
+) {
+    for (interaction, link) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            *page = link.0;
+        }
+    }
+}
+
+fn switch_settings_page(
+    page: Res<SettingsPage>,
+    settings: Res<Settings>,
+    menus: Query<Entity, With<SettingsMenu>>,
+    mut commands: Commands,
+) {
+    commands.remove_resource::<Rebinding>();
+    for menu in &menus {
+        commands
+            .entity(menu)
+            .despawn_descendants()
+            .with_children(|children| {
+                spawn_settings_page(children, &settings, *page);
+            });
+    }
+}
+
+/// Copies whatever the player changed in the menu into the settings.
+/// Widgets that were only just spawned are skipped, so opening the menu doesn't change anything by itself.
+fn apply_setting_changes(
+    sliders: Query<(&SettingField, Ref<Slider>)>,
+    toggles: Query<(&SettingField, Ref<Toggle>)>,
+    cycles: Query<(&SettingField, Ref<Cycle>)>,
+    mut settings: ResMut<Settings>,
+    mut page: ResMut<SettingsPage>,
+) {
+    for (field, slider) in &sliders {
+        if !slider.is_changed() || slider.is_added() {
+            continue;
+        }
+        match field {
+            SettingField::MasterVolume => settings.master_volume = slider.value,
+            SettingField::MusicVolume => settings.music_volume = slider.value,
+            SettingField::SfxVolume => settings.sfx_volume = slider.value,
+            SettingField::DefaultZoom => settings.default_zoom = slider.value,
+            SettingField::FollowSnappiness => settings.follow_snappiness = slider.value,
+            _ => {}
+        }
+    }
+
+    for (field, toggle) in &toggles {
+        if !toggle.is_changed() || toggle.is_added() {
+            continue;
+        }
+        match field {
+            SettingField::SoundEnabled => settings.sound_enabled = toggle.0,
+            SettingField::TimeOut => settings.lose_conditions.time_out = toggle.0,
+            SettingField::OneDetection => settings.lose_conditions.one_detection = toggle.0,
+            _ => {}
+        }
+    }
+
+    for (field, cycle) in &cycles {
+        if !cycle.is_changed() || cycle.is_added() {
+            continue;
+        }
+        match field {
+            SettingField::ControlDevice => {
+                *page = SettingsPage::Controls(InputDevice::ALL[cycle.selected]);
+            }
+            SettingField::MaxDeaths => {
+                settings.lose_conditions.max_deaths = MAX_DEATHS_OPTIONS[cycle.selected];
+            }
+            _ => {}
+        }
+    }
+}
+
+fn start_rebinding(
+    mut button_query: InteractionQuery<(&RebindButton, &mut Choice)>,
+    statuses: Query<&Children, With<RebindStatus>>,
+    mut texts: Query<&mut Text>,
+    page: Res<SettingsPage>,
+    mut commands: Commands,
+) {
+    let SettingsPage::Controls(device) = *page else {
+        return;
+    };
+    for (interaction, (button, mut choice)) in &mut button_query {
+        if !matches!(interaction, Interaction::Pressed) {
+            continue;
+        }
+        choice.0 = match device {
+            InputDevice::Keyboard => "Press a key...",
+            InputDevice::Gamepad => "Press a button...",
+        }
+        .to_string();
+        set_status(&statuses, &mut texts, "Escape to cancel");
+        commands.insert_resource(Rebinding {
+            device,
+            action: button.0,
+        });
+    }
+}
+
+/// Waits for the next key or gamepad button, and binds it unless another action already uses it
+fn capture_rebinding(
+    rebinding: Option<Res<Rebinding>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut settings: ResMut<Settings>,
+    mut rows: Query<(&RebindButton, &mut Choice)>,
+    statuses: Query<&Children, With<RebindStatus>>,
+    mut texts: Query<&mut Text>,
+    mut commands: Commands,
+) {
+    let Some(rebinding) = rebinding else {
+        return;
+    };
+
+    let cancelled = keys.just_pressed(KeyCode::Escape);
+    if !cancelled {
+        let pressed: Option<InputKind> = match rebinding.device {
+            InputDevice::Keyboard => keys.get_just_pressed().next().map(|key| (*key).into()),
+            InputDevice::Gamepad => gamepad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| button.button_type.into()),
+        };
+        let Some(input) = pressed else {
+            return;
+        };
+        let profile = settings.controls.profile_mut(rebinding.device);
+        if let Err(other) = profile.rebind(rebinding.action, input) {
+            let message = format!(
+                "{} is already used for {}",
+                input_name(&input),
+                other.name()
+            );
+            set_status(&statuses, &mut texts, &message);
+            return;
+        }
+    }
+
+    let bound = bound_input_name(&settings, rebinding.device, rebinding.action);
+    for (button, mut choice) in &mut rows {
+        if button.0 == rebinding.action {
+            choice.0.clone_from(&bound);
+        }
+    }
+    set_status(&statuses, &mut texts, REBIND_HINT);
+    commands.remove_resource::<Rebinding>();
+}
+
+/// Labels keep their text in a child, so look for it there
+fn set_status(
+    statuses: &Query<&Children, With<RebindStatus>>,
+    texts: &mut Query<&mut Text>,
+    message: &str,
+) {
+    for status_children in statuses {
+        for child in status_children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = message.to_string();
+            }
+        }
+    }
+}
+
+fn stop_rebinding(mut commands: Commands) {
+    commands.remove_resource::<Rebinding>();
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
+
+fn handle_settings_action(
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut button_query: InteractionQuery<&SettingsAction>,
+) {
+    for (interaction, action) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            match action {
+                SettingsAction::Back => next_screen.set(Screen::Title),
+            }
+        }
+    }
+}
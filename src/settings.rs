@@ -0,0 +1,132 @@
+//! Player preferences for audio, camera, controls and how a run can be lost.
+//! Native builds save them to a config file next to the game and load them again at startup.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::end_game::LoseConditions;
+use crate::input::ControlProfiles;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(Settings::load());
+
+    // reflection
+    app.register_type::<Settings>();
+}
+
+#[cfg(not(target_family = "wasm"))]
+const SETTINGS_PATH: &str = "settings.ron";
+
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Settings {
+    pub sound_enabled: bool,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    /// How far the camera is zoomed in while playing, smaller is closer
+    pub default_zoom: f32,
+    /// How quickly the camera catches up with the player
+    pub follow_snappiness: f32,
+    /// Bindings for keyboard and gamepad, kept separately so rebinding one doesn't disturb the other
+    pub controls: ControlProfiles,
+    /// The rules every new run starts with
+    pub lose_conditions: LoseConditions,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sound_enabled: true,
+            master_volume: 0.3,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            default_zoom: 0.23,
+            follow_snappiness: 7.0,
+            controls: ControlProfiles::default(),
+            lose_conditions: LoseConditions::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// How loud everything is overall, after muting
+    pub fn global_volume(&self) -> f32 {
+        if self.sound_enabled {
+            self.master_volume
+        } else {
+            0.0
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load() -> Self {
+        match std::fs::read_to_string(SETTINGS_PATH) {
+            Ok(contents) => Self::from_ron(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid settings in {}: {}", SETTINGS_PATH, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn load() -> Self {
+        Self::default()
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                std::fs::write(SETTINGS_PATH, contents).map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(()) => info!("Saved settings to {}", SETTINGS_PATH),
+            Err(e) => warn!("Couldn't save settings to {}: {}", SETTINGS_PATH, e),
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn save(&self) {}
+
+    #[cfg(any(test, not(target_family = "wasm")))]
+    fn from_ron(contents: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputDevice, PlayerAction};
+
+    #[test]
+    fn round_trips_through_ron() {
+        let mut settings = Settings {
+            sound_enabled: false,
+            music_volume: 0.5,
+            ..default()
+        };
+        settings
+            .controls
+            .profile_mut(InputDevice::Gamepad)
+            .rebind(PlayerAction::Roll, GamepadButtonType::East.into())
+            .unwrap();
+
+        let contents = ron::to_string(&settings).unwrap();
+
+        assert_eq!(Settings::from_ron(&contents).unwrap(), settings);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let settings = Settings::from_ron("(music_volume: 0.1)").unwrap();
+
+        assert_eq!(settings.music_volume, 0.1);
+        assert_eq!(settings.master_volume, Settings::default().master_volume);
+        assert_eq!(settings.controls, ControlProfiles::default());
+    }
+}
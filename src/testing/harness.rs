@@ -0,0 +1,423 @@
+//! Runs the game's simulation headlessly, without a window, a GPU or an LDtk project, so whole scenarios can be
+//! played out in tests.
+//!
+//! Levels are drawn as ASCII maps, one character per grid square, with the top row of the string being the top of
+//! the level. Leading and trailing whitespace on each row is ignored, so maps can be indented along with the test.
+//!
+//! | Character         | Square                                         |
+//! |-------------------|------------------------------------------------|
+//! | `#`               | wall                                           |
+//! | `.`               | floor                                          |
+//! | `@`               | where the player starts                        |
+//! | `>` `<` `^` `v`   | an idle enemy, facing the way the arrow points |
+//! | `k`               | key                                            |
+//! | `L`               | locked door                                    |
+//! | `G`               | goal                                           |
+//!
+//! Everything but the player is spawned the way LDtk would spawn it, and then turned into game entities by the
+//! same systems that handle real levels.
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy_ecs_ldtk::ldtk::{FieldInstance, FieldValue};
+use bevy_ecs_ldtk::prelude::LdtkProject;
+use bevy_ecs_ldtk::{EntityInstance, GridCoords, LevelEvent, LevelIid};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::game::assets::ImageAssets;
+use crate::game::end_game::EndGameCondition;
+use crate::game::grid::GridPosition;
+use crate::game::line_of_sight::{vision, PlayerLineOfSightBundle};
+use crate::game::movement::{GridMovement, RollState};
+use crate::game::session::SessionSeed;
+use crate::game::spawn::door::CanOpenDoors;
+use crate::game::spawn::enemy::LdtkEnemy;
+use crate::game::spawn::exit::LdtkLockedDoor;
+use crate::game::spawn::goal::LdtkGoal;
+use crate::game::spawn::health::{CanReceiveDamage, SpawnPointGridPosition};
+use crate::game::spawn::keys::LdtkKey;
+use crate::game::spawn::level::{LevelVisionBlockers, LevelWalls, GRID_SIZE};
+use crate::game::spawn::player::Player;
+use crate::game::{ai, campaign, end_game, grid, movement, rng, snapshot, spawn, threat};
+use crate::input::PlayerAction;
+use crate::screen::{PlayState, Screen};
+use crate::settings::Settings;
+
+/// How often the simulation ticks, the same as the game
+const TICK_RATE_HZ: f64 = 60.0;
+
+/// A level running headlessly, stepped one fixed tick at a time
+pub struct TestLevel {
+    pub app: App,
+}
+
+/// Every [`EndGameCondition`] that's been triggered, in order
+#[derive(Resource, Default)]
+struct EndGameConditions(Vec<EndGameCondition>);
+
+impl TestLevel {
+    /// Spawns the level drawn in `map`, see the [module docs](self) for what each character means
+    pub fn from_ascii(map: &str) -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()));
+        // the assets the game's plugins expect, which never load since there's no renderer
+        app.init_asset::<Image>();
+        app.init_asset::<TextureAtlasLayout>();
+        app.init_asset::<LdtkProject>();
+        app.add_event::<LevelEvent>();
+        let images = ImageAssets::new(app.world().resource::<AssetServer>());
+        app.insert_resource(images);
+
+        // every update is exactly one fixed tick
+        let tick = Time::<Fixed>::from_hz(TICK_RATE_HZ);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(tick.timestep()));
+        app.insert_resource(tick);
+
+        app.init_resource::<Settings>();
+        app.insert_resource(SessionSeed(0));
+        app.insert_state(Screen::Playing);
+        app.add_sub_state::<PlayState>();
+        crate::configure_app_sets(&mut app);
+
+        // only the simulation, none of the screens, audio, dialog, fog or ghosts
+        app.add_plugins((
+            grid::plugin,
+            movement::plugin,
+            campaign::plugin,
+            end_game::plugin,
+            threat::plugin,
+            spawn::plugin,
+            ai::plugin,
+            vision::plugin,
+            snapshot::plugin,
+            rng::plugin,
+        ));
+
+        app.init_resource::<EndGameConditions>();
+        app.observe(
+            |trigger: Trigger<EndGameCondition>, mut conditions: ResMut<EndGameConditions>| {
+                conditions.0.push(*trigger.event());
+            },
+        );
+
+        spawn_ascii_level(app.world_mut(), map);
+        // let the spawned LDtk entities be turned into game entities, then tell everything the level's ready
+        app.update();
+        app.world_mut()
+            .send_event(LevelEvent::Transformed(LevelIid::new("test-level")));
+        app.update();
+
+        Self { app }
+    }
+
+    /// Runs the simulation for this many fixed ticks
+    pub fn step(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    /// Holds down `action` until it's released
+    pub fn press(&mut self, action: PlayerAction) {
+        self.player_mut::<ActionState<PlayerAction>>()
+            .press(&action);
+    }
+
+    /// Lets go of `action`
+    pub fn release(&mut self, action: PlayerAction) {
+        self.player_mut::<ActionState<PlayerAction>>()
+            .release(&action);
+    }
+
+    /// The grid square the player is in
+    pub fn player_square(&mut self) -> IVec2 {
+        self.app
+            .world_mut()
+            .query_filtered::<&GridPosition, With<Player>>()
+            .single(self.app.world())
+            .coordinates
+            .round()
+            .as_ivec2()
+    }
+
+    pub fn resource<R: Resource>(&self) -> &R {
+        self.app.world().resource::<R>()
+    }
+
+    /// How the run ended, if it has
+    pub fn end_game_condition(&self) -> Option<EndGameCondition> {
+        self.resource::<EndGameConditions>().0.first().copied()
+    }
+
+    /// How many entities match the query filter `F`
+    pub fn count<F: bevy::ecs::query::QueryFilter + 'static>(&mut self) -> usize {
+        self.app
+            .world_mut()
+            .run_system_once(|query: Query<(), F>| query.iter().count())
+    }
+
+    fn player_mut<C: Component>(&mut self) -> Mut<'_, C> {
+        let world = self.app.world_mut();
+        let player = world.query_filtered::<Entity, With<Player>>().single(world);
+        world
+            .get_mut::<C>(player)
+            .expect("player should have component")
+    }
+}
+
+fn spawn_ascii_level(world: &mut World, map: &str) {
+    let rows: Vec<&str> = map
+        .lines()
+        .map(str::trim)
+        .filter(|row| !row.is_empty())
+        .collect();
+    let level_height = rows.len() as i32;
+    let level_width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as i32;
+
+    let mut walls = LevelWalls {
+        level_width,
+        level_height,
+        ..default()
+    };
+    let mut vision_blockers = LevelVisionBlockers {
+        level_width,
+        level_height,
+        ..default()
+    };
+
+    for (ldtk_y, row) in rows.iter().enumerate() {
+        for (x, square) in row.chars().enumerate() {
+            // LDtk counts rows down from the top, the grid counts them up from the bottom
+            let ldtk_coords = IVec2::new(x as i32, ldtk_y as i32);
+            let coords = GridCoords::new(x as i32, level_height - ldtk_y as i32 - 1);
+            match square {
+                '#' => {
+                    walls.wall_locations.insert(coords);
+                    vision_blockers.vision_blocker_locations.insert(coords);
+                }
+                '@' => spawn_player(world, coords),
+                '>' | '<' | '^' | 'v' => {
+                    let facing = match square {
+                        '>' => "Right",
+                        '<' => "Left",
+                        '^' => "Up",
+                        _ => "Down",
+                    };
+                    world.spawn((
+                        LdtkEnemy,
+                        coords,
+                        enemy_instance(ldtk_coords, facing),
+                        square_bundle(),
+                    ));
+                }
+                'k' => {
+                    world.spawn((LdtkKey, coords, square_bundle()));
+                }
+                'L' => {
+                    world.spawn((LdtkLockedDoor, coords, square_bundle()));
+                }
+                'G' => {
+                    world.spawn((LdtkGoal, coords, square_bundle()));
+                }
+                '.' => {}
+                _ => panic!("unknown square {:?} in test level", square),
+            }
+        }
+    }
+
+    world.insert_resource(walls);
+    world.insert_resource(vision_blockers);
+}
+
+/// What every entity in the level needs to be placed and bumped into, without a sprite to size it
+fn square_bundle() -> (Transform, Visibility, Aabb) {
+    let half_square = GRID_SIZE as f32 / 2.;
+    (
+        Transform::default(),
+        Visibility::default(),
+        Aabb::from_min_max(Vec3::splat(-half_square), Vec3::splat(half_square)),
+    )
+}
+
+fn enemy_instance(ldtk_coords: IVec2, facing: &str) -> EntityInstance {
+    EntityInstance {
+        iid: format!("test-enemy-{}-{}", ldtk_coords.x, ldtk_coords.y),
+        grid: ldtk_coords,
+        field_instances: vec![FieldInstance {
+            identifier: "InitialFacing".to_string(),
+            value: FieldValue::Enum(Some(facing.to_string())),
+            field_instance_type: String::new(),
+            tile: None,
+            def_uid: 0,
+            real_editor_values: vec![],
+        }],
+        ..default()
+    }
+}
+
+/// Like the real player, minus everything that's only there to be seen or heard
+fn spawn_player(world: &mut World, coords: GridCoords) {
+    let start = Vec2::new(coords.x as f32, coords.y as f32);
+    world.spawn((
+        Name::new("Player"),
+        Player,
+        Sprite::default(),
+        square_bundle(),
+        SpawnPointGridPosition(start),
+        CanReceiveDamage,
+        CanOpenDoors,
+        GridPosition::new(start.x, start.y),
+        GridMovement::default(),
+        RollState::default(),
+        ActionState::<PlayerAction>::default(),
+        PlayerLineOfSightBundle::default(),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::end_game::RunStats;
+    use crate::game::spawn::exit::{CanBeUnlocked, NumKeysPickedUp};
+    use crate::game::spawn::keys::CanPickup;
+    use crate::game::spawn::level::BlocksMovement;
+
+    /// Enough ticks to walk a few squares
+    const A_FEW_SECONDS: u32 = 3 * TICK_RATE_HZ as u32;
+
+    #[test]
+    fn the_player_walks_until_a_wall() {
+        let mut level = TestLevel::from_ascii(
+            "
+            #####
+            #@..#
+            #####
+            ",
+        );
+        assert_eq!(level.player_square(), IVec2::new(1, 1));
+
+        level.press(PlayerAction::MoveRight);
+        level.step(A_FEW_SECONDS);
+
+        assert_eq!(level.player_square(), IVec2::new(3, 1));
+    }
+
+    #[test]
+    fn enemies_detect_the_player_in_front_of_them() {
+        let mut level = TestLevel::from_ascii(
+            "
+            ########
+            #>.@...#
+            ########
+            ",
+        );
+
+        level.step(2 * A_FEW_SECONDS);
+
+        assert!(level.resource::<RunStats>().detections > 0);
+    }
+
+    #[test]
+    fn enemies_dont_detect_the_player_behind_them() {
+        let mut level = TestLevel::from_ascii(
+            "
+            ########
+            #<.@...#
+            ########
+            ",
+        );
+
+        level.step(2 * A_FEW_SECONDS);
+
+        assert_eq!(level.resource::<RunStats>().detections, 0);
+    }
+
+    #[test]
+    fn walking_into_an_enemy_kills_the_player() {
+        let mut level = TestLevel::from_ascii(
+            "
+            #######
+            #@..>.#
+            #######
+            ",
+        );
+
+        level.press(PlayerAction::MoveRight);
+        for _ in 0..A_FEW_SECONDS {
+            level.step(1);
+            if level.resource::<RunStats>().deaths > 0 {
+                break;
+            }
+        }
+
+        assert_eq!(level.resource::<RunStats>().deaths, 1);
+        assert_eq!(level.player_square(), IVec2::new(1, 1));
+    }
+
+    #[test]
+    fn keys_unlock_doors() {
+        let mut level = TestLevel::from_ascii(
+            "
+            #######
+            #@.k.L#
+            #######
+            ",
+        );
+        assert_eq!(level.count::<With<CanBeUnlocked>>(), 1);
+        assert_eq!(
+            level.count::<(With<CanBeUnlocked>, With<BlocksMovement>)>(),
+            1
+        );
+
+        level.press(PlayerAction::MoveRight);
+        level.step(A_FEW_SECONDS / 2);
+        assert_eq!(level.count::<With<CanPickup>>(), 0);
+
+        level.step(A_FEW_SECONDS);
+        assert_eq!(
+            level.count::<(With<CanBeUnlocked>, With<BlocksMovement>)>(),
+            0
+        );
+        assert_eq!(level.resource::<NumKeysPickedUp>().0, 0);
+    }
+
+    #[test]
+    fn locked_doors_stay_shut_without_a_key() {
+        let mut level = TestLevel::from_ascii(
+            "
+            ######
+            #@..L#
+            ######
+            ",
+        );
+
+        level.press(PlayerAction::MoveRight);
+        level.step(A_FEW_SECONDS);
+
+        assert_eq!(
+            level.count::<(With<CanBeUnlocked>, With<BlocksMovement>)>(),
+            1
+        );
+        assert_eq!(level.player_square(), IVec2::new(3, 1));
+    }
+
+    #[test]
+    fn reaching_the_goal_of_the_last_level_wins() {
+        let mut level = TestLevel::from_ascii(
+            "
+            ######
+            #@..G#
+            ######
+            ",
+        );
+        assert_eq!(level.end_game_condition(), None);
+
+        level.press(PlayerAction::MoveRight);
+        level.step(A_FEW_SECONDS);
+
+        assert_eq!(level.end_game_condition(), Some(EndGameCondition::Win));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod harness;
+
 #[macro_export]
 macro_rules! assert_vec2_close {
     ($a:expr, $b:expr) => {
@@ -11,12 +11,12 @@ pub mod prelude {
     pub use super::{
         interaction::{InteractionPalette, InteractionQuery},
         palette as ui_palette,
-        widgets::{Containers as _, Widgets as _},
+        widgets::{Choice, Containers as _, Cycle, Slider, Toggle, Widgets as _},
     };
 }
 
 use bevy::prelude::*;
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins(interaction::plugin);
+    app.add_plugins((interaction::plugin, widgets::plugin));
 }
@@ -1,23 +1,147 @@
 //! Helper traits for creating common widgets.
 
-use bevy::{ecs::system::EntityCommands, prelude::*, ui::Val::*};
+use bevy::{ecs::system::EntityCommands, prelude::*, ui::RelativeCursorPosition, ui::Val::*};
 
-use super::{interaction::InteractionPalette, palette::*};
+use super::{interaction::InteractionPalette, interaction::InteractionQuery, palette::*};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Slider>();
+    app.register_type::<Toggle>();
+    app.register_type::<Cycle>();
+    app.register_type::<Choice>();
+    app.add_systems(
+        Update,
+        (
+            (drag_sliders, press_toggles, press_cycles),
+            (update_slider_fills, update_widget_values),
+        )
+            .chain(),
+    );
+}
 
 /// An extension trait for spawning UI widgets.
 pub trait Widgets {
     /// Spawn a simple button with text.
-    fn button(&mut self, text: impl Into<String>) -> EntityCommands;
+    fn button(&mut self, text: impl Into<String>) -> EntityCommands<'_>;
 
     /// Spawn a simple header label. Bigger than [`Widgets::label`].
-    fn header(&mut self, text: impl Into<String>) -> EntityCommands;
+    fn header(&mut self, text: impl Into<String>) -> EntityCommands<'_>;
 
     /// Spawn a simple text label.
-    fn label(&mut self, text: impl Into<String>) -> EntityCommands;
+    fn label(&mut self, text: impl Into<String>) -> EntityCommands<'_>;
+
+    /// Spawn a labelled bar that can be dragged to pick a value. Read the [`Slider`] on the returned entity.
+    fn slider(&mut self, text: impl Into<String>, slider: Slider) -> EntityCommands<'_>;
+
+    /// Spawn a labelled on/off button. Read the [`Toggle`] on the returned entity.
+    fn toggle(&mut self, text: impl Into<String>, value: bool) -> EntityCommands<'_>;
+
+    /// Spawn a labelled picker that steps through options with arrow buttons.
+    /// Read the [`Cycle`] on the returned entity.
+    fn cycle(
+        &mut self,
+        text: impl Into<String>,
+        options: Vec<String>,
+        selected: usize,
+    ) -> EntityCommands<'_>;
+
+    /// Spawn a labelled button showing a value, e.g. what an action is bound to. Read the [`Choice`] on the
+    /// returned entity, and watch its [`Interaction`] to tell when it's picked.
+    fn choice(&mut self, text: impl Into<String>, value: impl Into<String>) -> EntityCommands<'_>;
 }
 
+/// A value between `min` and `max`, picked with [`Widgets::slider`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Slider {
+    pub fn new(value: f32, min: f32, max: f32) -> Self {
+        Self {
+            value: value.clamp(min, max),
+            min,
+            max,
+        }
+    }
+
+    /// How far along the bar the value is, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        (self.value - self.min) / (self.max - self.min)
+    }
+
+    pub fn set_fraction(&mut self, fraction: f32) {
+        self.value = self.min + fraction.clamp(0.0, 1.0) * (self.max - self.min);
+    }
+}
+
+/// An on/off value, picked with [`Widgets::toggle`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Toggle(pub bool);
+
+/// One of a list of options, picked with [`Widgets::cycle`].
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Cycle {
+    pub options: Vec<String>,
+    pub selected: usize,
+}
+
+impl Cycle {
+    /// Move through the options, wrapping around at either end
+    pub fn step(&mut self, steps: isize) {
+        if self.options.is_empty() {
+            return;
+        }
+        let len = self.options.len() as isize;
+        self.selected = (self.selected as isize + steps).rem_euclid(len) as usize;
+    }
+
+    fn current(&self) -> &str {
+        self.options.get(self.selected).map_or("", String::as_str)
+    }
+}
+
+/// The value shown on a button spawned with [`Widgets::choice`].
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Choice(pub String);
+
+/// The part of a slider that can be clicked and dragged
+#[derive(Component)]
+struct SliderTrack;
+
+/// The filled in part of a slider's track
+#[derive(Component)]
+struct SliderFill;
+
+/// The button that flips a toggle
+#[derive(Component)]
+struct ToggleButton;
+
+/// The arrow buttons either side of a cycle, and which way they step
+#[derive(Component)]
+struct CycleButton(isize);
+
+/// Text showing the current value of a slider, toggle or cycle
+#[derive(Component)]
+struct WidgetValue;
+
+const WIDGET_ROW_WIDTH: f32 = 500.0;
+const WIDGET_ROW_HEIGHT: f32 = 40.0;
+const SLIDER_TRACK_WIDTH: f32 = 180.0;
+const SLIDER_TRACK_HEIGHT: f32 = 16.0;
+const SMALL_BUTTON_SIZE: f32 = 40.0;
+
 impl<T: Spawn> Widgets for T {
-    fn button(&mut self, text: impl Into<String>) -> EntityCommands {
+    fn button(&mut self, text: impl Into<String>) -> EntityCommands<'_> {
         let mut entity = self.spawn((
             Name::new("Button"),
             ButtonBundle {
@@ -53,7 +177,7 @@ impl<T: Spawn> Widgets for T {
         entity
     }
 
-    fn header(&mut self, text: impl Into<String>) -> EntityCommands {
+    fn header(&mut self, text: impl Into<String>) -> EntityCommands<'_> {
         let mut entity = self.spawn((
             Name::new("Header"),
             NodeBundle {
@@ -84,7 +208,7 @@ impl<T: Spawn> Widgets for T {
         entity
     }
 
-    fn label(&mut self, text: impl Into<String>) -> EntityCommands {
+    fn label(&mut self, text: impl Into<String>) -> EntityCommands<'_> {
         let mut entity = self.spawn((
             Name::new("Label"),
             NodeBundle {
@@ -113,17 +237,304 @@ impl<T: Spawn> Widgets for T {
         });
         entity
     }
+
+    fn slider(&mut self, text: impl Into<String>, slider: Slider) -> EntityCommands<'_> {
+        let mut entity = widget_row(self, "Slider", text.into());
+        entity.insert(slider).with_children(|children| {
+            children
+                .spawn((
+                    Name::new("Slider Track"),
+                    SliderTrack,
+                    ButtonBundle {
+                        style: Style {
+                            width: Px(SLIDER_TRACK_WIDTH),
+                            height: Px(SLIDER_TRACK_HEIGHT),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(NODE_BACKGROUND.darker(0.2)),
+                        ..default()
+                    },
+                    RelativeCursorPosition::default(),
+                ))
+                .with_children(|children| {
+                    children.spawn((
+                        Name::new("Slider Fill"),
+                        SliderFill,
+                        NodeBundle {
+                            style: Style {
+                                width: Percent(slider.fraction() * 100.0),
+                                height: Percent(100.0),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(LABEL_TEXT),
+                            ..default()
+                        },
+                    ));
+                });
+            widget_value(children, slider_value_text(&slider), 70.0);
+        });
+        entity
+    }
+
+    fn toggle(&mut self, text: impl Into<String>, value: bool) -> EntityCommands<'_> {
+        let toggle = Toggle(value);
+        let mut entity = widget_row(self, "Toggle", text.into());
+        entity.insert(toggle).with_children(|children| {
+            small_button(children, "Switch", 120.0).insert(ToggleButton);
+            widget_value(children, toggle_value_text(&toggle), 70.0);
+        });
+        entity
+    }
+
+    fn cycle(
+        &mut self,
+        text: impl Into<String>,
+        options: Vec<String>,
+        selected: usize,
+    ) -> EntityCommands<'_> {
+        let cycle = Cycle { options, selected };
+        let current = cycle.current().to_string();
+        let mut entity = widget_row(self, "Cycle", text.into());
+        entity.insert(cycle).with_children(|children| {
+            small_button(children, "<", SMALL_BUTTON_SIZE).insert(CycleButton(-1));
+            widget_value(children, current, 170.0);
+            small_button(children, ">", SMALL_BUTTON_SIZE).insert(CycleButton(1));
+        });
+        entity
+    }
+
+    fn choice(&mut self, text: impl Into<String>, value: impl Into<String>) -> EntityCommands<'_> {
+        let choice = Choice(value.into());
+        let mut entity = widget_row(self, "Choice", text.into());
+        entity
+            .insert((
+                choice.clone(),
+                Interaction::default(),
+                BackgroundColor(Color::NONE),
+                InteractionPalette {
+                    none: Color::NONE,
+                    hovered: BUTTON_HOVERED_BACKGROUND,
+                    pressed: BUTTON_PRESSED_BACKGROUND,
+                },
+            ))
+            .with_children(|children| {
+                widget_value(children, choice.0, 220.0);
+            });
+        entity
+    }
+}
+
+/// A row with a label on the left, and whatever the widget needs on the right
+fn widget_row<'a, T: Spawn>(spawner: &'a mut T, name: &str, text: String) -> EntityCommands<'a> {
+    let mut entity = spawner.spawn((
+        Name::new(name.to_string()),
+        NodeBundle {
+            style: Style {
+                width: Px(WIDGET_ROW_WIDTH),
+                height: Px(WIDGET_ROW_HEIGHT),
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                column_gap: Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    entity.with_children(|children| {
+        children.spawn((
+            Name::new("Widget Label"),
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: 24.0,
+                    color: LABEL_TEXT,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                flex_grow: 1.0,
+                ..default()
+            }),
+        ));
+    });
+    entity
+}
+
+fn widget_value(children: &mut ChildBuilder, text: impl Into<String>, width: f32) {
+    children.spawn((
+        Name::new("Widget Value"),
+        WidgetValue,
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font_size: 24.0,
+                color: BUTTON_TEXT,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            width: Px(width),
+            ..default()
+        })
+        .with_text_justify(JustifyText::Center),
+    ));
+}
+
+fn small_button<'a>(children: &'a mut ChildBuilder, text: &str, width: f32) -> EntityCommands<'a> {
+    let mut entity = children.spawn((
+        Name::new("Small Button"),
+        ButtonBundle {
+            style: Style {
+                width: Px(width),
+                height: Px(SMALL_BUTTON_SIZE),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(NODE_BACKGROUND),
+            ..default()
+        },
+        InteractionPalette {
+            none: NODE_BACKGROUND,
+            hovered: BUTTON_HOVERED_BACKGROUND,
+            pressed: BUTTON_PRESSED_BACKGROUND,
+        },
+    ));
+    entity.with_children(|children| {
+        children.spawn((
+            Name::new("Button Text"),
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: 24.0,
+                    color: BUTTON_TEXT,
+                    ..default()
+                },
+            ),
+        ));
+    });
+    entity
+}
+
+fn slider_value_text(slider: &Slider) -> String {
+    format!("{:.2}", slider.value)
+}
+
+fn toggle_value_text(toggle: &Toggle) -> &'static str {
+    if toggle.0 {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+/// Holding the mouse down on a slider's track moves the value to wherever the cursor is
+fn drag_sliders(
+    tracks: Query<(&Interaction, &RelativeCursorPosition, &Parent), With<SliderTrack>>,
+    mut sliders: Query<&mut Slider>,
+) {
+    for (interaction, cursor, parent) in &tracks {
+        if !matches!(interaction, Interaction::Pressed) {
+            continue;
+        }
+        let (Some(cursor), Ok(mut slider)) = (cursor.normalized, sliders.get_mut(parent.get()))
+        else {
+            continue;
+        };
+        let mut moved = *slider;
+        moved.set_fraction(cursor.x);
+        if moved != *slider {
+            *slider = moved;
+        }
+    }
+}
+
+fn press_toggles(
+    mut button_query: Query<(&Interaction, &Parent), (Changed<Interaction>, With<ToggleButton>)>,
+    mut toggles: Query<&mut Toggle>,
+) {
+    for (interaction, parent) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            if let Ok(mut toggle) = toggles.get_mut(parent.get()) {
+                toggle.0 = !toggle.0;
+            }
+        }
+    }
+}
+
+fn press_cycles(
+    mut button_query: InteractionQuery<(&Parent, &CycleButton)>,
+    mut cycles: Query<&mut Cycle>,
+) {
+    for (interaction, (parent, button)) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            if let Ok(mut cycle) = cycles.get_mut(parent.get()) {
+                cycle.step(button.0);
+            }
+        }
+    }
+}
+
+fn update_slider_fills(
+    mut fills: Query<(&Parent, &mut Style), With<SliderFill>>,
+    tracks: Query<&Parent, With<SliderTrack>>,
+    sliders: Query<&Slider, Changed<Slider>>,
+) {
+    for (track, mut style) in &mut fills {
+        let Ok(root) = tracks.get(track.get()) else {
+            continue;
+        };
+        if let Ok(slider) = sliders.get(root.get()) {
+            style.width = Percent(slider.fraction() * 100.0);
+        }
+    }
+}
+
+fn update_widget_values(
+    mut values: Query<(&Parent, &mut Text), With<WidgetValue>>,
+    widgets: Query<
+        (
+            Option<&Slider>,
+            Option<&Toggle>,
+            Option<&Cycle>,
+            Option<&Choice>,
+        ),
+        Or<(
+            Changed<Slider>,
+            Changed<Toggle>,
+            Changed<Cycle>,
+            Changed<Choice>,
+        )>,
+    >,
+) {
+    for (parent, mut text) in &mut values {
+        let Ok((slider, toggle, cycle, choice)) = widgets.get(parent.get()) else {
+            continue;
+        };
+        let value = if let Some(slider) = slider {
+            slider_value_text(slider)
+        } else if let Some(toggle) = toggle {
+            toggle_value_text(toggle).to_string()
+        } else if let Some(cycle) = cycle {
+            cycle.current().to_string()
+        } else if let Some(choice) = choice {
+            choice.0.clone()
+        } else {
+            continue;
+        };
+        text.sections[0].value = value;
+    }
 }
 
 /// An extension trait for spawning UI containers.
 pub trait Containers {
     /// Spawns a root node that covers the full screen
     /// and centers its content horizontally and vertically.
-    fn ui_root(&mut self) -> EntityCommands;
+    fn ui_root(&mut self) -> EntityCommands<'_>;
 }
 
 impl Containers for Commands<'_, '_> {
-    fn ui_root(&mut self) -> EntityCommands {
+    fn ui_root(&mut self) -> EntityCommands<'_> {
         self.spawn((
             Name::new("UI Root"),
             NodeBundle {
@@ -145,17 +556,48 @@ impl Containers for Commands<'_, '_> {
 
 /// An internal trait for types that can spawn entities.
 trait Spawn {
-    fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityCommands;
+    fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityCommands<'_>;
 }
 
 impl Spawn for Commands<'_, '_> {
-    fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityCommands {
+    fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityCommands<'_> {
         self.spawn(bundle)
     }
 }
 
 impl Spawn for ChildBuilder<'_> {
-    fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityCommands {
+    fn spawn<B: Bundle>(&mut self, bundle: B) -> EntityCommands<'_> {
         self.spawn(bundle)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slider_fraction_is_clamped_to_range() {
+        let mut slider = Slider::new(0.5, 0.0, 2.0);
+        assert_eq!(slider.fraction(), 0.25);
+
+        slider.set_fraction(1.5);
+        assert_eq!(slider.value, 2.0);
+
+        slider.set_fraction(-1.0);
+        assert_eq!(slider.value, 0.0);
+    }
+
+    #[test]
+    fn cycle_wraps_around() {
+        let mut cycle = Cycle {
+            options: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            selected: 0,
+        };
+
+        cycle.step(-1);
+        assert_eq!(cycle.current(), "c");
+
+        cycle.step(2);
+        assert_eq!(cycle.current(), "b");
+    }
+}